@@ -1,7 +1,7 @@
 // rust/compiler/rustc_mir_transform/lib.rs
 // need to modify
 + pub mod safedrop_check;
-+ use safedrop_check::{SafeDropGraph, FuncMap};
++ use safedrop_check::{SafeDropGraph, FuncMap, cached_analysis, record_analysis, emit_warning, join_selected};
 ...
 pub fn provide(providers: &mut Providers) {
     ...
@@ -23,14 +23,19 @@ fn safedrop_check<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> () {
     if tcx.is_mir_available(def_id) {
         let body = tcx.optimized_mir(def_id);
         let mut func_map = FuncMap::new();
+        // the function and the summaries of its callees are unchanged since the last build.
+        if let Some(entry) = cached_analysis(tcx, def_id, &mut func_map, true){
+            emit_warning(def_id, &entry.message.unwrap());
+            return;
+        }
         let mut safedrop_graph = SafeDropGraph::new(&body, tcx, def_id);
         safedrop_graph.solve_scc();
-        safedrop_graph.safedrop_check(0, tcx, &mut func_map);
-        if safedrop_graph.visit_times <= 10000{
-            safedrop_graph.output_warning();
-        }
-        else{
-            println!("over_visited: {:?}", def_id);
+        safedrop_graph.analyze(tcx, &mut func_map);
+        if safedrop_graph.join_mode && join_selected(tcx, def_id) == false{
+            println!("over_visited, analyzed by merging paths: {:?}", def_id);
         }
+        safedrop_graph.output_warning();
+        let message = safedrop_graph.warning_message();
+        record_analysis(tcx, &mut safedrop_graph, &func_map, Some(message));
     }
 }
\ No newline at end of file
@@ -18,5 +18,7 @@ fn analysis(tcx: TyCtxt<'_>, (): ()) -> Result<()> {
 
     + sess.time("safedrop_check", || {
     +   tcx.hir().par_body_owners(|def_id| tcx.ensure().safedrop_check(def_id));
+    +   rustc_mir_transform::safedrop_check::save_cache(tcx);
+    +   rustc_mir_transform::safedrop_check::SUMMARY_STORE.clear();
     + });
 }
\ No newline at end of file
@@ -0,0 +1,463 @@
+use rustc_middle::mir::BinOp;
+use rustc_middle::mir::UnOp;
+use rustc_middle::mir::Operand;
+use rustc_middle::mir::Rvalue;
+use rustc_middle::mir::LocalDecls;
+use rustc_middle::mir::terminator::TerminatorKind;
+use rustc_middle::ty;
+use rustc_middle::ty::ParamEnv;
+use rustc_middle::ty::Ty;
+use rustc_middle::ty::TyCtxt;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::fx::FxHashSet;
+use super::SafeDropGraph;
+use super::graph::BlockNode;
+
+
+//the relational operators of the comparisons feeding a switchInt.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum Relation{
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Relation{
+    pub fn from_binop(op: BinOp) -> Option<Relation>{
+        match op{
+            BinOp::Eq => Some(Relation::Eq),
+            BinOp::Ne => Some(Relation::Ne),
+            BinOp::Lt => Some(Relation::Lt),
+            BinOp::Le => Some(Relation::Le),
+            BinOp::Gt => Some(Relation::Gt),
+            BinOp::Ge => Some(Relation::Ge),
+            _ => None,
+        }
+    }
+
+    pub fn negate(self) -> Relation{
+        match self{
+            Relation::Eq => Relation::Ne,
+            Relation::Ne => Relation::Eq,
+            Relation::Lt => Relation::Ge,
+            Relation::Le => Relation::Gt,
+            Relation::Gt => Relation::Le,
+            Relation::Ge => Relation::Lt,
+        }
+    }
+
+    //the relation with the operands swapped.
+    pub fn flip(self) -> Relation{
+        match self{
+            Relation::Lt => Relation::Gt,
+            Relation::Le => Relation::Ge,
+            Relation::Gt => Relation::Lt,
+            Relation::Ge => Relation::Le,
+            other => other,
+        }
+    }
+
+    pub fn holds(self, left: i128, right: i128) -> bool{
+        match self{
+            Relation::Eq => left == right,
+            Relation::Ne => left != right,
+            Relation::Lt => left < right,
+            Relation::Le => left <= right,
+            Relation::Gt => left > right,
+            Relation::Ge => left >= right,
+        }
+    }
+}
+
+//an operand of a comparison: a local without projections or an integer constant.
+//a null pointer is the constant 0.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum Term{
+    Local(usize),
+    Const(i128),
+}
+
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub struct Comparison{
+    pub relation: Relation,
+    pub left: Term,
+    pub right: Term,
+}
+
+impl Comparison{
+    pub fn new(relation: Relation, left: Term, right: Term) -> Comparison{
+        Comparison { relation: relation, left: left, right: right }
+    }
+
+    pub fn negate(&self) -> Comparison{
+        Comparison::new(self.relation.negate(), self.left, self.right)
+    }
+
+    pub fn mentions(&self, local: usize) -> bool{
+        self.left == Term::Local(local) || self.right == Term::Local(local)
+    }
+}
+
+//the effect of a statement on the path constraint, kept in the order of the statements.
+#[derive(Debug,Clone)]
+pub enum Definition{
+    // the local gets a value we know nothing about.
+    Unknown(usize),
+    // the local gets a value satisfying the comparison.
+    Fact(usize, Comparison),
+    // the local holds the result of the comparison.
+    Compare(usize, Comparison),
+}
+
+impl Definition{
+    pub fn local(&self) -> usize{
+        match self{
+            Definition::Unknown(local) | Definition::Fact(local, _) | Definition::Compare(local, _) => *local,
+        }
+    }
+}
+
+//the comparisons known on the current path, used to prune the switchInt arms that cannot be taken.
+#[derive(Debug,Clone,Default)]
+pub struct PathConstraint{
+    // the comparison whose result is held by a local.
+    pub comparisons: FxHashMap<usize, Comparison>,
+    // the comparisons known to hold on the current path.
+    pub facts: Vec<Comparison>,
+    // the locals borrowed mutably, they may change behind a pointer and are never constrained.
+    pub borrowed: FxHashSet<usize>,
+}
+
+impl PathConstraint{
+    pub fn new() -> PathConstraint{
+        PathConstraint { comparisons: FxHashMap::default(), facts: Vec::new(), borrowed: FxHashSet::default() }
+    }
+
+    fn stable(&self, cmp: &Comparison) -> bool{
+        self.borrowed.iter().all(|local| cmp.mentions(*local) == false)
+    }
+
+    //the local is overwritten, everything known about its old value is dropped.
+    pub fn define(&mut self, local: usize){
+        self.comparisons.remove(&local);
+        self.comparisons.retain(|_, cmp| cmp.mentions(local) == false);
+        self.facts.retain(|cmp| cmp.mentions(local) == false);
+    }
+
+    pub fn apply(&mut self, definition: &Definition){
+        self.define(definition.local());
+        match definition{
+            Definition::Unknown(_) => {},
+            Definition::Fact(_, cmp) => {
+                if self.stable(cmp){
+                    self.facts.push(*cmp);
+                }
+            },
+            Definition::Compare(local, cmp) => {
+                if self.stable(cmp){
+                    self.comparisons.insert(*local, *cmp);
+                }
+            },
+        }
+    }
+
+    //the switchInt on the local takes the value, or any value other than the excluded ones.
+    //return false if the path becomes infeasible.
+    pub fn assume(&mut self, local: usize, value: Option<i128>, excluded: &Vec<i128>) -> bool{
+        if self.borrowed.contains(&local){
+            return true;
+        }
+        let term = Term::Local(local);
+        let cmp = self.comparisons.get(&local).cloned();
+        match value{
+            Some(value) => {
+                self.facts.push(Comparison::new(Relation::Eq, term, Term::Const(value)));
+                if let Some(cmp) = cmp{
+                    self.facts.push(if value == 0 { cmp.negate() } else { cmp });
+                }
+            },
+            None => {
+                for value in excluded.iter(){
+                    self.facts.push(Comparison::new(Relation::Ne, term, Term::Const(*value)));
+                }
+                if let Some(cmp) = cmp{
+                    if excluded.contains(&0){
+                        self.facts.push(cmp);
+                    }
+                    else if excluded.contains(&1){
+                        self.facts.push(cmp.negate());
+                    }
+                }
+            },
+        }
+        return self.feasible();
+    }
+
+    //check the facts with the equality, interval and nullness domain. each local is given the interval
+    //of its values, the intervals are narrowed by the facts until nothing changes or a contradiction shows up.
+    pub fn feasible(&self) -> bool{
+        let mut ranges: FxHashMap<usize, (i128, i128)> = FxHashMap::default();
+        let mut changed = true;
+        let mut rounds = 0;
+        while changed && rounds <= self.facts.len() + 1{
+            changed = false;
+            rounds += 1;
+            for cmp in self.facts.iter(){
+                let (relation, left, right) = match (cmp.left, cmp.right){
+                    (Term::Const(l), Term::Const(r)) => {
+                        if cmp.relation.holds(l, r) == false{
+                            return false;
+                        }
+                        continue;
+                    },
+                    (Term::Const(c), Term::Local(x)) => (cmp.relation.flip(), x, Term::Const(c)),
+                    (Term::Local(x), right) => (cmp.relation, x, right),
+                };
+                let left_range = range_of(&ranges, left);
+                let right_range = match right{
+                    Term::Const(c) => (c, c),
+                    Term::Local(y) => {
+                        if y == left{
+                            if relation.holds(0, 0) == false{
+                                return false;
+                            }
+                            continue;
+                        }
+                        range_of(&ranges, y)
+                    },
+                };
+                let (new_left, new_right) = match narrow(relation, left_range, right_range){
+                    Some(narrowed) => narrowed,
+                    None => return false,
+                };
+                if new_left != left_range{
+                    ranges.insert(left, new_left);
+                    changed = true;
+                }
+                if let Term::Local(y) = right{
+                    if new_right != right_range{
+                        ranges.insert(y, new_right);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        return true;
+    }
+
+    //only the comparisons known on both paths are kept at a join point.
+    pub fn join(&self, other: &PathConstraint) -> PathConstraint{
+        let mut result = PathConstraint::new();
+        result.borrowed = self.borrowed.clone();
+        for (local, cmp) in self.comparisons.iter(){
+            if other.comparisons.get(local) == Some(cmp){
+                result.comparisons.insert(*local, *cmp);
+            }
+        }
+        for cmp in self.facts.iter(){
+            if other.facts.contains(cmp) && result.facts.contains(cmp) == false{
+                result.facts.push(*cmp);
+            }
+        }
+        return result;
+    }
+
+    //the comparisons and the facts in a canonical order, equal for the same constraint.
+    pub fn canonical(&self) -> (Vec<(usize, Comparison)>, Vec<Comparison>){
+        let mut comparisons: Vec<(usize, Comparison)> = self.comparisons.iter().map(|c| (*c.0, *c.1)).collect();
+        comparisons.sort();
+        let mut facts = self.facts.clone();
+        facts.sort();
+        facts.dedup();
+        return (comparisons, facts);
+    }
+}
+
+fn range_of(ranges: &FxHashMap<usize, (i128, i128)>, local: usize) -> (i128, i128){
+    return *ranges.get(&local).unwrap_or(&(i128::MIN, i128::MAX));
+}
+
+//narrow the intervals of both sides of `left relation right`, None if no values satisfy it.
+fn narrow(relation: Relation, left: (i128, i128), right: (i128, i128)) -> Option<((i128, i128), (i128, i128))>{
+    let (mut l, mut r) = (left, right);
+    match relation{
+        Relation::Eq => {
+            l = (l.0.max(r.0), l.1.min(r.1));
+            r = l;
+        },
+        Relation::Ne => {
+            // only a single value can be cut from the bounds of the other side.
+            if r.0 == r.1{
+                if l.0 == r.0 { l.0 = l.0.saturating_add(1); }
+                if l.1 == r.0 { l.1 = l.1.saturating_sub(1); }
+            }
+            if l.0 == l.1{
+                if r.0 == l.0 { r.0 = r.0.saturating_add(1); }
+                if r.1 == l.0 { r.1 = r.1.saturating_sub(1); }
+            }
+        },
+        Relation::Lt => {
+            l.1 = l.1.min(r.1.saturating_sub(1));
+            r.0 = r.0.max(l.0.saturating_add(1));
+        },
+        Relation::Le => {
+            l.1 = l.1.min(r.1);
+            r.0 = r.0.max(l.0);
+        },
+        Relation::Gt => {
+            let (new_r, new_l) = narrow(Relation::Lt, r, l)?;
+            l = new_l;
+            r = new_r;
+        },
+        Relation::Ge => {
+            let (new_r, new_l) = narrow(Relation::Le, r, l)?;
+            l = new_l;
+            r = new_r;
+        },
+    }
+    if l.0 > l.1 || r.0 > r.1{
+        return None;
+    }
+    return Some((l, r));
+}
+
+//the value of an integer, bool or char constant as a mathematical integer.
+pub fn const_value<'tcx>(tcx: TyCtxt<'tcx>, param_env: ParamEnv<'tcx>, operand: &Operand<'tcx>, ty: Ty<'tcx>) -> Option<i128>{
+    let constant = operand.constant()?;
+    let bits = constant.literal.try_eval_bits(tcx, param_env, ty)?;
+    return bits_value(tcx, param_env, ty, bits);
+}
+
+//interpret the bits of a switchInt value or a constant with the signedness of its type.
+pub fn bits_value<'tcx>(tcx: TyCtxt<'tcx>, param_env: ParamEnv<'tcx>, ty: Ty<'tcx>, bits: u128) -> Option<i128>{
+    if ty.is_signed(){
+        let size = tcx.layout_of(param_env.and(ty)).ok()?.size;
+        return Some(size.sign_extend(bits) as i128);
+    }
+    if bits > i128::MAX as u128{
+        return None;
+    }
+    return Some(bits as i128);
+}
+
+//the operand of a comparison, None if it is neither a local nor a known constant.
+pub fn term<'tcx>(tcx: TyCtxt<'tcx>, param_env: ParamEnv<'tcx>, operand: &Operand<'tcx>, ty: Ty<'tcx>) -> Option<Term>{
+    match operand{
+        Operand::Copy(p) | Operand::Move(p) => {
+            if p.projection.is_empty(){
+                return Some(Term::Local(p.local.as_usize()));
+            }
+            return None;
+        },
+        Operand::Constant(_) => {
+            return const_value(tcx, param_env, operand, ty).map(|value| Term::Const(value));
+        },
+    }
+}
+
+//the definition of the local assigned by the rvalue.
+pub fn rvalue_definition<'tcx>(tcx: TyCtxt<'tcx>, param_env: ParamEnv<'tcx>, local_decls: &LocalDecls<'tcx>, left: usize, rvalue: &Rvalue<'tcx>) -> Definition{
+    match rvalue{
+        Rvalue::Use(ref x) => {
+            if let Some(right) = term(tcx, param_env, x, x.ty(local_decls, tcx)){
+                return Definition::Fact(left, Comparison::new(Relation::Eq, Term::Local(left), right));
+            }
+        },
+        Rvalue::BinaryOp(op, ref operands) => {
+            let ty = operands.0.ty(local_decls, tcx);
+            if let (Some(relation), Some(l), Some(r)) = (Relation::from_binop(*op),
+                term(tcx, param_env, &operands.0, ty), term(tcx, param_env, &operands.1, ty)){
+                return Definition::Compare(left, Comparison::new(relation, l, r));
+            }
+        },
+        Rvalue::UnaryOp(UnOp::Not, ref x) => {
+            let ty = x.ty(local_decls, tcx);
+            if ty.is_bool(){
+                if let Some(operand) = term(tcx, param_env, x, ty){
+                    return Definition::Compare(left, Comparison::new(Relation::Eq, operand, Term::Const(0)));
+                }
+            }
+        },
+        // a reference or the address of a place is never null.
+        Rvalue::Ref(..) | Rvalue::AddressOf(..) => {
+            return Definition::Fact(left, Comparison::new(Relation::Ne, Term::Local(left), Term::Const(0)));
+        },
+        _ => {},
+    }
+    return Definition::Unknown(left);
+}
+
+//the definition of the destination of a call, the nullness of the pointers is known for a few functions.
+pub fn call_definition<'tcx>(tcx: TyCtxt<'tcx>, func: &Operand<'tcx>, args: &Vec<Operand<'tcx>>, left: usize) -> Definition{
+    if let Operand::Constant(ref constant) = func{
+        if let ty::FnDef(target_id, _) = constant.literal.ty().kind(){
+            let path = tcx.def_path_str(*target_id);
+            if path.contains("ptr") == false{
+                return Definition::Unknown(left);
+            }
+            // closures have no item name.
+            let name = match tcx.opt_item_name(*target_id){
+                Some(name) => name,
+                None => return Definition::Unknown(left),
+            };
+            match name.as_str(){
+                "is_null" => {
+                    if let Some(Operand::Copy(p) | Operand::Move(p)) = args.get(0){
+                        if p.projection.is_empty(){
+                            let cmp = Comparison::new(Relation::Eq, Term::Local(p.local.as_usize()), Term::Const(0));
+                            return Definition::Compare(left, cmp);
+                        }
+                    }
+                },
+                "null" | "null_mut" => {
+                    return Definition::Fact(left, Comparison::new(Relation::Eq, Term::Local(left), Term::Const(0)));
+                },
+                _ => {},
+            }
+        }
+    }
+    return Definition::Unknown(left);
+}
+
+impl<'tcx> SafeDropGraph<'tcx>{
+    //apply what the switchInt of the block tells on the edge to next_index, return false if the edge cannot be taken.
+    pub fn assume_edge(&mut self, current_block: &BlockNode<'tcx>, next_index: usize, tcx: TyCtxt<'tcx>) -> bool{
+        let terminator = match current_block.switch_stmts.first(){
+            Some(terminator) => terminator.clone(),
+            None => return true,
+        };
+        if let TerminatorKind::SwitchInt { ref discr, switch_ty, ref targets } = terminator.kind{
+            let local = match discr.place(){
+                Some(p) if p.projection.is_empty() => p.local.as_usize(),
+                _ => return true,
+            };
+            let param_env = tcx.param_env(self.def_id);
+            let mut values = Vec::new();
+            let mut others = Vec::new();
+            for (bits, target) in targets.iter(){
+                let value = match bits_value(tcx, param_env, switch_ty, bits){
+                    Some(value) => value,
+                    None => return true,
+                };
+                if target.as_usize() == next_index{
+                    values.push(value);
+                }
+                else{
+                    others.push(value);
+                }
+            }
+            if targets.otherwise().as_usize() == next_index{
+                if values.is_empty(){
+                    return self.constraint.assume(local, None, &others);
+                }
+            }
+            else if values.len() == 1{
+                return self.constraint.assume(local, Some(values[0]), &Vec::new());
+            }
+        }
+        return true;
+    }
+}
@@ -1,28 +1,249 @@
 
 use crate::SafeDropGraph;
 use rustc_data_structures::fx::FxHashSet;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_middle::mir::SourceInfo;
+use rustc_middle::ty;
+use rustc_middle::ty::TyCtxt;
 use rustc_span::def_id::DefId;
+use super::tools::merge_alias;
+use super::node::FieldKey;
 
 
 impl<'tcx> SafeDropGraph<'tcx>{
     //can also use the format to check.
     //these function calls are the functions whose MIRs can not be fetched.
     pub fn corner_handle(&mut self, _left_ssa: usize, _merge_vec: &Vec::<usize>, _move_set: &mut FxHashSet<usize>, def_id: DefId) -> bool{
-        // function::call_mut
-        if def_id.index.as_usize() == 3430{
-            return true;
-        }
         //function::iterator::next
         if def_id.index.as_usize() == 8476{
             return true;
         }
-        //intrinsic_offset
-        if def_id.index.as_usize() == 1709{
-            return true;
-        }
         return false;
     }
 
+    //the methods of Option which change the variant behind the reference argument,
+    //e.g. take() leaves None (discriminant 0) and insert() leaves Some (discriminant 1).
+    pub fn variant_handle(&mut self, merge_vec: &Vec::<usize>, def_id: DefId, tcx: TyCtxt<'tcx>){
+        if merge_vec.len() < 2 || tcx.def_path_str(def_id).contains("Option") == false{
+            return;
+        }
+        let name = match tcx.opt_item_name(def_id){
+            Some(name) => name,
+            None => return,
+        };
+        let discr = match name.as_str(){
+            "take" => 0,
+            "insert" | "replace" | "get_or_insert" | "get_or_insert_with" => 1,
+            _ => return,
+        };
+        let option = self.nodes[merge_vec[1]].alias[0];
+        self.constant_bool.insert(option, discr);
+    }
+
+    //the built-in models of the std APIs which transfer, duplicate, relinquish or exchange the ownership of a value.
+    //the results do not depend on the MIR of std. return true if the call is handled.
+    pub fn std_model(&mut self, merge_vec: &Vec::<usize>, move_set: &mut FxHashSet<usize>, def_id: DefId, tcx: TyCtxt<'tcx>, info: &SourceInfo, alive: isize) -> bool{
+        let (owner, item) = match std_api(tcx, def_id){
+            Some(api) => api,
+            None => return false,
+        };
+        let left_ssa = merge_vec[0];
+        match (owner.as_str(), item.as_str()){
+            // the raw pointer (or the leaked reference) and the owner stand for the same object.
+            ("Box", "into_raw") | ("Box", "leak") | ("Box", "from_raw")
+            | ("Vec", "from_raw_parts") | ("Vec", "as_mut_ptr") | ("Vec", "as_ptr")
+            | ("String", "from_raw_parts") | ("CString", "into_raw") | ("CString", "from_raw")
+            | ("Rc", "into_raw") | ("Rc", "from_raw") | ("NonNull", "as_ptr") => {
+                if merge_vec.len() > 1{
+                    merge_alias(move_set, left_ssa, merge_vec[1], &mut self.nodes);
+                }
+            },
+            // the pointer is the first field of the returned (ptr, len, cap).
+            ("Vec", "into_raw_parts") => {
+                if merge_vec.len() > 1{
+                    let ptr = self.son_node(left_ssa, self.nodes[left_ssa].index, FieldKey::Field(0), None, tcx);
+                    merge_alias(move_set, ptr, merge_vec[1], &mut self.nodes);
+                }
+            },
+            // a fresh allocation.
+            ("alloc", "alloc") | ("alloc", "alloc_zeroed") => {
+                self.fill_alive(left_ssa, alive);
+                self.nodes[left_ssa].alias = vec![left_ssa];
+            },
+            ("alloc", "dealloc") => {
+                if merge_vec.len() > 1{
+                    self.dead_node(merge_vec[1], 99999, info, false);
+                }
+            },
+            // the old allocation is freed and a fresh one is returned.
+            ("alloc", "realloc") => {
+                if merge_vec.len() > 1{
+                    self.dead_node(merge_vec[1], 99999, info, false);
+                }
+                self.fill_alive(left_ssa, alive);
+                self.nodes[left_ssa].alias = vec![left_ssa];
+            },
+            // a bitwise copy of the pointee, the result is a second owner of its memory.
+            ("ptr", "read") | ("ptr", "read_unaligned") | ("ptr", "read_volatile") | ("mem", "transmute_copy")
+            | ("pointer", "read") | ("pointer", "read_unaligned") | ("pointer", "read_volatile") => {
+                if merge_vec.len() > 1{
+                    let src = self.deref_node(merge_vec[1]);
+                    merge_alias(move_set, left_ssa, src, &mut self.nodes);
+                }
+            },
+            // the pointee of the destination becomes a second owner of the memory of the source.
+            ("intrinsics", "copy") | ("intrinsics", "copy_nonoverlapping") | ("ptr", "copy") | ("ptr", "copy_nonoverlapping")
+            | ("pointer", "copy_to") | ("pointer", "copy_to_nonoverlapping") => {
+                if merge_vec.len() > 2{
+                    self.copy_model(merge_vec[1], merge_vec[2], move_set, alive);
+                }
+            },
+            ("pointer", "copy_from") | ("pointer", "copy_from_nonoverlapping") => {
+                if merge_vec.len() > 2{
+                    self.copy_model(merge_vec[2], merge_vec[1], move_set, alive);
+                }
+            },
+            // the pointee is overwritten without being dropped, it takes over the value.
+            ("ptr", "write") | ("ptr", "write_unaligned") | ("ptr", "write_volatile")
+            | ("pointer", "write") | ("pointer", "write_unaligned") | ("pointer", "write_volatile") => {
+                if merge_vec.len() > 2{
+                    let dst = self.deref_node(merge_vec[1]);
+                    self.fill_alive(dst, alive);
+                    merge_alias(move_set, dst, merge_vec[2], &mut self.nodes);
+                }
+            },
+            // the ownership is relinquished, the value is never dropped. the place it is moved out of holds
+            // nothing, even a field whose move does not mark the place.
+            ("mem", "forget") => {
+                if merge_vec.len() > 1{
+                    self.nodes[merge_vec[1]].moved = true;
+                }
+            },
+            ("ManuallyDrop", "new") | ("ManuallyDrop", "into_inner") | ("MaybeUninit", "new")
+            | ("MaybeUninit", "assume_init") | ("MaybeUninit", "as_ptr") | ("MaybeUninit", "as_mut_ptr") => {
+                if merge_vec.len() > 1{
+                    merge_alias(move_set, left_ssa, merge_vec[1], &mut self.nodes);
+                }
+            },
+            // the wrapped value is dropped in place, a second drop is a double free.
+            ("ManuallyDrop", "drop") | ("MaybeUninit", "assume_init_drop") => {
+                if merge_vec.len() > 1{
+                    let slot = self.deref_node(merge_vec[1]);
+                    self.dead_node(slot, 99999, info, false);
+                }
+            },
+            // the wrapped value is read out and left in the slot, which becomes a second owner.
+            ("ManuallyDrop", "take") | ("MaybeUninit", "assume_init_read") => {
+                if merge_vec.len() > 1{
+                    let slot = self.deref_node(merge_vec[1]);
+                    merge_alias(move_set, left_ssa, slot, &mut self.nodes);
+                }
+            },
+            // not initialized, the slot owns nothing.
+            ("MaybeUninit", "uninit") | ("MaybeUninit", "zeroed") | ("MaybeUninit", "uninit_array") => {
+                self.fill_alive(left_ssa, alive);
+                self.nodes[left_ssa].alias = vec![left_ssa];
+            },
+            ("MaybeUninit", "write") => {
+                if merge_vec.len() > 2{
+                    let slot = self.deref_node(merge_vec[1]);
+                    self.fill_alive(slot, alive);
+                    merge_alias(move_set, slot, merge_vec[2], &mut self.nodes);
+                    merge_alias(move_set, left_ssa, merge_vec[1], &mut self.nodes);
+                }
+            },
+            // the two places exchange their values.
+            ("mem", "swap") | ("ptr", "swap") | ("pointer", "swap") => {
+                if merge_vec.len() > 2{
+                    let x = self.deref_node(merge_vec[1]);
+                    let y = self.deref_node(merge_vec[2]);
+                    self.swap_nodes(x, y, tcx);
+                }
+            },
+            // the old value is returned and the new one is installed in the place.
+            ("mem", "replace") | ("ptr", "replace") | ("pointer", "replace") => {
+                if merge_vec.len() > 2{
+                    let dest = self.deref_node(merge_vec[1]);
+                    self.swap_nodes(left_ssa, dest, tcx);
+                    self.fill_alive(dest, alive);
+                    self.nodes[dest].moved = false;
+                    self.nodes[dest].drop_span = None;
+                    // the variant of the new value.
+                    match self.constant_bool.get(&merge_vec[2]).cloned(){
+                        Some(discr) => self.constant_bool.insert(dest, discr),
+                        None => self.constant_bool.remove(&dest),
+                    };
+                    merge_alias(move_set, dest, merge_vec[2], &mut self.nodes);
+                }
+            },
+            // the old value is returned and a default value, which owns nothing, is left in the place.
+            ("mem", "take") => {
+                if merge_vec.len() > 1{
+                    let dest = self.deref_node(merge_vec[1]);
+                    self.swap_nodes(left_ssa, dest, tcx);
+                    self.fill_alive(dest, alive);
+                    self.nodes[dest].alias = vec![dest];
+                    self.nodes[dest].sons = FxHashMap::default();
+                    self.nodes[dest].moved = false;
+                    self.nodes[dest].drop_span = None;
+                    // the variant of the default value is not known.
+                    self.constant_bool.remove(&dest);
+                }
+            },
+            _ => return false,
+        }
+        return true;
+    }
+
+    //exchange the values of two nodes, i.e. their alias sets, liveness, variants, drop states and fields.
+    //a node holding its own value keeps holding its own (now exchanged) value.
+    pub fn swap_nodes(&mut self, x: usize, y: usize, tcx: TyCtxt<'tcx>){
+        if x == y{
+            return;
+        }
+        let rename = |alias: &Vec<usize>| -> Vec<usize> {
+            alias.iter().map(|i| if *i == x { y } else if *i == y { x } else { *i }).collect()
+        };
+        let x_alias = rename(&self.nodes[y].alias);
+        let y_alias = rename(&self.nodes[x].alias);
+        self.nodes[x].alias = x_alias;
+        self.nodes[y].alias = y_alias;
+        let (x_alive, y_alive) = (self.nodes[x].alive, self.nodes[y].alive);
+        self.nodes[x].alive = y_alive;
+        self.nodes[y].alive = x_alive;
+        let (x_maybe_dead, y_maybe_dead) = (self.nodes[x].maybe_dead, self.nodes[y].maybe_dead);
+        self.nodes[x].maybe_dead = y_maybe_dead;
+        self.nodes[y].maybe_dead = x_maybe_dead;
+        let (x_moved, y_moved) = (self.nodes[x].moved, self.nodes[y].moved);
+        self.nodes[x].moved = y_moved;
+        self.nodes[y].moved = x_moved;
+        let (x_span, y_span) = (self.nodes[x].drop_span, self.nodes[y].drop_span);
+        self.nodes[x].drop_span = y_span;
+        self.nodes[y].drop_span = x_span;
+        let (x_variant, y_variant) = (self.constant_bool.remove(&x), self.constant_bool.remove(&y));
+        if let Some(discr) = y_variant{
+            self.constant_bool.insert(x, discr);
+        }
+        if let Some(discr) = x_variant{
+            self.constant_bool.insert(y, discr);
+        }
+        let mut keys: Vec<FieldKey> = self.nodes[x].sons.keys().chain(self.nodes[y].sons.keys()).cloned().collect();
+        keys.sort();
+        keys.dedup();
+        for key in keys{
+            let x_son = self.son_node(x, self.nodes[x].index, key, None, tcx);
+            let y_son = self.son_node(y, self.nodes[y].index, key, None, tcx);
+            self.swap_nodes(x_son, y_son, tcx);
+        }
+    }
+
+    fn copy_model(&mut self, src: usize, dst: usize, move_set: &mut FxHashSet<usize>, alive: isize){
+        let src = self.deref_node(src);
+        let dst = self.deref_node(dst);
+        self.fill_alive(dst, alive);
+        merge_alias(move_set, dst, src, &mut self.nodes);
+    }
+
     //the dangling pointer occuring in some functions like drop() is reasonable. 
     pub fn should_check(def_id: DefId) -> bool{
         let def_str = format!("{:?}",def_id);
@@ -42,6 +263,25 @@ impl<'tcx> SafeDropGraph<'tcx>{
     }
 }
 
+//pointer arithmetic, the derived pointer points into the same allocation as the base pointer.
+pub fn is_pointer_arith<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> bool{
+    let path = tcx.def_path_str(def_id);
+    // closures have no item name.
+    let name = match tcx.opt_item_name(def_id){
+        Some(name) => name,
+        None => return false,
+    };
+    match name.as_str(){
+        //intrinsic_offset
+        "offset" | "arith_offset" if path.contains("intrinsics") => true,
+        //the methods of *const T and *mut T
+        "offset" | "add" | "sub" | "wrapping_offset" | "wrapping_add" | "wrapping_sub"
+        | "byte_offset" | "byte_add" | "byte_sub" => path.contains("<impl *"),
+        "slice_from_raw_parts" | "slice_from_raw_parts_mut" => path.contains("ptr"),
+        _ => false,
+    }
+}
+
 //these adt structs use the Rc-kind drop instruction, which we do not focus on. 
 pub fn is_corner_adt(str: String) -> bool{
     if let Some(_) = str.find("cell::RefMut"){
@@ -54,4 +294,44 @@ pub fn is_corner_adt(str: String) -> bool{
         return true;
     }
     return false;
-}
\ No newline at end of file
+}
+
+//the value in ManuallyDrop or MaybeUninit is never dropped with its owner, only explicitly.
+pub fn is_manual_adt(str: String) -> bool{
+    if let Some(_) = str.find("ManuallyDrop"){
+        return true;
+    }
+    if let Some(_) = str.find("MaybeUninit"){
+        return true;
+    }
+    return false;
+}
+
+//the type (or the module for a free function) and the name of a function defined in std, alloc or core.
+pub fn std_api<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> Option<(String, String)>{
+    match tcx.crate_name(def_id.krate).as_str(){
+        "std" | "alloc" | "core" => {},
+        _ => return None,
+    }
+    let item = match tcx.opt_item_name(def_id){
+        Some(name) => name.to_string(),
+        None => return None,
+    };
+    let owner = match tcx.impl_of_method(def_id){
+        Some(impl_id) => {
+            match tcx.type_of(impl_id).kind(){
+                ty::Adt(adt_def, _) => tcx.item_name(adt_def.did()).to_string(),
+                // the methods of *const T and *mut T.
+                ty::RawPtr(_) => "pointer".to_string(),
+                _ => return None,
+            }
+        },
+        None => {
+            match tcx.opt_item_name(tcx.parent(def_id)){
+                Some(name) => name.to_string(),
+                None => return None,
+            }
+        },
+    };
+    return Some((owner, item));
+}
@@ -0,0 +1,145 @@
+use rustc_middle::mir::StatementKind;
+use rustc_middle::mir::terminator::TerminatorKind;
+use rustc_middle::mir::Body;
+use rustc_middle::mir::BasicBlock;
+use rustc_middle::mir::Field;
+use rustc_middle::mir::Place;
+use rustc_middle::mir::Rvalue;
+use rustc_middle::mir::Terminator;
+use rustc_middle::ty;
+use rustc_middle::ty::TyCtxt;
+use rustc_target::abi::VariantIdx;
+use rustc_data_structures::fx::FxHashMap;
+use super::SafeDropGraph;
+use super::graph::BlockNode;
+use super::node::Node;
+use super::constraint::PathConstraint;
+
+
+//a suspension point of a generator: the block dropping the saved locals when the suspended generator is
+//cancelled, and the block where it is resumed.
+#[derive(Debug,Clone)]
+pub struct Suspension{
+    pub cancel: usize,
+    pub resume: Option<usize>,
+}
+
+//the state of a path reaching a suspension, the cancellation and the resumption continue from it.
+#[derive(Debug,Clone)]
+pub struct SuspendState{
+    pub block: usize,
+    pub nodes: Vec<Node>,
+    pub constant_bool: FxHashMap<usize, usize>,
+    pub constraint: PathConstraint,
+}
+
+impl<'tcx> SafeDropGraph<'tcx>{
+    //record the state of a path reaching a suspension, the suspensions after a resumption are not recorded.
+    pub fn suspend_state(&mut self, bb_index: usize){
+        if self.resumed || self.suspensions.contains_key(&bb_index) == false{
+            return;
+        }
+        let state = SuspendState {
+            block: bb_index,
+            nodes: self.nodes.clone(),
+            constant_bool: self.constant_bool.clone(),
+            constraint: self.constraint.clone(),
+        };
+        self.suspend_states.push(state);
+    }
+}
+
+//the state machine of a generator or an async fn. the optimized MIR is the resume function, a suspension
+//stores the state in the generator and returns. a suspended generator is either resumed, which is a new call of
+//the resume function dispatched by the state, or dropped, which drops the saved locals of the state (the
+//cancellation of a future). the suspending returns stay leaves of the CFG, the cancellations are blocks of
+//their own. return the suspensions by their returns.
+pub fn generator_blocks<'tcx>(body: &Body<'tcx>, tcx: TyCtxt<'tcx>, blocks: &mut Vec<BlockNode<'tcx>>, father_block: &mut Vec<usize>) -> FxHashMap<usize, Suspension>{
+    let mut suspensions = FxHashMap::default();
+    let layout = match body.generator_layout(){
+        Some(layout) => layout,
+        None => return suspensions,
+    };
+    let param_env = tcx.param_env(body.source.def_id());
+    let basicblocks = body.basic_blocks();
+    // the blocks where the generator is resumed from each state.
+    let mut resume = FxHashMap::<usize, usize>::default();
+    for data in basicblocks.iter(){
+        let terminator = data.terminator();
+        if let TerminatorKind::SwitchInt { ref discr, switch_ty: _, ref targets } = terminator.kind{
+            let switch_local = match discr.place(){
+                Some(p) => p.local,
+                None => continue,
+            };
+            let on_state = data.statements.iter().any(|statement| {
+                if let StatementKind::Assign(ref assign) = statement.kind {
+                    if let Rvalue::Discriminant(ref p) = assign.1 {
+                        return assign.0.local == switch_local && is_generator(body, tcx, p);
+                    }
+                }
+                false
+            });
+            if on_state{
+                for (value, target) in targets.iter(){
+                    resume.insert(value as usize, target.as_usize());
+                }
+            }
+        }
+    }
+    for i in 0..basicblocks.len(){
+        let data = &basicblocks[BasicBlock::from(i)];
+        match data.terminator().kind{
+            TerminatorKind::Return => {},
+            _ => continue,
+        }
+        // the state stored before returning, only the suspended states can be resumed or dropped.
+        let mut suspend = None;
+        for statement in data.statements.iter(){
+            if let StatementKind::SetDiscriminant { ref place, variant_index } = statement.kind {
+                if is_generator(body, tcx, place) && is_suspended(variant_index){
+                    suspend = Some((**place, variant_index, statement.source_info));
+                }
+            }
+        }
+        let (place, variant_index, source_info) = match suspend{
+            Some(suspend) => suspend,
+            None => continue,
+        };
+        // the cancellation: drop the locals saved in the state.
+        let drop_index = blocks.len();
+        let mut drop_block = BlockNode::new(drop_index, false);
+        let state: Place<'tcx> = tcx.mk_place_downcast_unnamed(place, variant_index);
+        for (field, saved_local) in layout.variant_fields[variant_index].iter().enumerate(){
+            let field_ty = layout.field_tys[*saved_local];
+            if field_ty.needs_drop(tcx, param_env) == false{
+                continue;
+            }
+            let kind = TerminatorKind::Drop {
+                place: tcx.mk_place_field(state, Field::from_usize(field), field_ty),
+                target: BasicBlock::from(i),
+                unwind: None,
+            };
+            drop_block.drops.push(Terminator { source_info: source_info, kind: kind });
+        }
+        blocks.push(drop_block);
+        father_block.push(drop_index);
+        let suspension = Suspension {
+            cancel: drop_index,
+            resume: resume.get(&variant_index.as_usize()).cloned(),
+        };
+        suspensions.insert(i, suspension);
+    }
+    return suspensions;
+}
+
+fn is_generator<'tcx>(body: &Body<'tcx>, tcx: TyCtxt<'tcx>, place: &Place<'tcx>) -> bool{
+    if let ty::Generator(..) = place.ty(&body.local_decls, tcx).ty.kind(){
+        return true;
+    }
+    return false;
+}
+
+//the states after the unresumed, returned and poisoned ones stand for the suspension points.
+pub fn is_suspended(variant_index: VariantIdx) -> bool{
+    return variant_index.as_usize() >= ty::GeneratorSubsts::RESERVED_VARIANTS;
+}
@@ -0,0 +1,29 @@
+use rustc_middle::ty::TyCtxt;
+use rustc_span::def_id::DefId;
+use super::SafeDropGraph;
+use super::node::Node;
+use super::tools::kind;
+
+
+impl<'tcx> SafeDropGraph<'tcx>{
+    //the node standing for the memory of a static or a thread local, created on its first use in the function.
+    //it is found by the static it stands for, so that it is kept or dropped together with the other nodes of a path.
+    pub fn global_node(&mut self, def_id: DefId, tcx: TyCtxt<'tcx>) -> usize{
+        for (i, node) in self.nodes.iter().enumerate(){
+            if node.global == Some(def_id){
+                return i;
+            }
+        }
+        let index = self.nodes.len();
+        self.nodes.push(new_global_node(tcx, def_id, index));
+        return index;
+    }
+}
+
+pub fn new_global_node<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId, index: usize) -> Node{
+    // a static is never dropped.
+    let mut node = Node::new(index, index, false, true);
+    node.kind = kind(tcx.type_of(def_id));
+    node.global = Some(def_id);
+    return node;
+}
@@ -13,11 +13,23 @@ use rustc_data_structures::fx::FxHashSet;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_middle::mir::Operand;
 use rustc_middle::mir::Rvalue;
+use rustc_middle::mir::AggregateKind;
+use rustc_middle::mir::Field;
+use rustc_middle::mir::ProjectionElem;
+use rustc_middle::mir::BorrowKind;
+use rustc_middle::mir::Mutability;
+use rustc_middle::ty;
+use rustc_middle::ty::Ty;
 use rustc_span::Span;
 use super::BugRecords;
 use super::tools::*;
 use super::node::Node;
+use super::node::FieldKey;
 use super::node::ReturnResults;
+use super::constraint::*;
+use super::generator::generator_blocks;
+use super::generator::Suspension;
+use super::generator::SuspendState;
 
 
 //self-defined assignments structure. 
@@ -25,8 +37,14 @@ use super::node::ReturnResults;
 pub struct Assignment<'tcx>{
     pub left: Place<'tcx>,
     pub right: Place<'tcx>,
+    // 0: copy or borrow, 1: move, 2: box, 3: discriminant, 4: the storage of the left local ends, 5: a read through the right pointer,
+    // 6: the left place is rebuilt by an aggregate, 7: the enum variant of the left place is written.
     pub atype: usize,
     pub span: Span,
+    // the right side is the node of a static or a thread local instead of the right place.
+    pub right_global: Option<DefId>,
+    // the discriminant of the written variant.
+    pub discr: usize,
 }
 
 impl<'tcx> Assignment<'tcx>{
@@ -36,8 +54,24 @@ impl<'tcx> Assignment<'tcx>{
             right: right,
             atype: atype,
             span: span,
+            right_global: None,
+            discr: 0,
         }
     }
+
+    //the left place gets a pointer to a static, the right place is unused.
+    pub fn global(left: Place<'tcx>, static_id: DefId, span: Span)->Assignment<'tcx>{
+        let mut assign = Assignment::new(left, left, 0, span);
+        assign.right_global = Some(static_id);
+        return assign;
+    }
+
+    //the left place holds the variant with the discriminant, which decides the later switchInt on it.
+    pub fn variant(left: Place<'tcx>, discr: usize, span: Span)->Assignment<'tcx>{
+        let mut assign = Assignment::new(left, left, 7, span);
+        assign.discr = discr;
+        return assign;
+    }
 }
 
 //self-defined basicblock structure.
@@ -46,6 +80,8 @@ pub struct BlockNode<'tcx>{
     pub index: usize,
     pub is_cleanup: bool,
     pub next: FxHashSet<usize>,
+    //store the successors in the CFG, which are kept when the SCC is shrinked into its root node.
+    pub cfg_next: FxHashSet<usize>,
     pub assignments: Vec<Assignment<'tcx>>,
     pub calls: Vec<Terminator<'tcx>>,
     pub drops: Vec<Terminator<'tcx>>,
@@ -55,6 +91,8 @@ pub struct BlockNode<'tcx>{
     pub const_value: Vec::<(usize, usize)>,
     //store switch stmts in current block for the path filtering in path-sensitive analysis.
     pub switch_stmts: Vec::<Terminator<'tcx>>,
+    //store the definitions of the locals in this block, which constrain the later switchInt.
+    pub definitions: Vec::<Definition>,
 }
 
 impl<'tcx> BlockNode<'tcx>{
@@ -63,17 +101,20 @@ impl<'tcx> BlockNode<'tcx>{
             index: index,
             is_cleanup: is_cleanup,
             next: FxHashSet::<usize>::default(),
+            cfg_next: FxHashSet::<usize>::default(),
             assignments: Vec::<Assignment<'tcx>>::new(),
             calls: Vec::<Terminator<'tcx>>::new(),
             drops: Vec::<Terminator<'tcx>>::new(),
             sub_blocks: Vec::<usize>::new(),
             const_value: Vec::<(usize, usize)>::new(),
             switch_stmts: Vec::<Terminator<'tcx>>::new(),
+            definitions: Vec::<Definition>::new(),
         }
     }
 
     pub fn push(&mut self, index: usize){
         self.next.insert(index);
+        self.cfg_next.insert(index);
     }
 }
 
@@ -85,6 +126,8 @@ pub struct SafeDropGraph<'tcx>{
     // contains all blocks in the CFG
     pub blocks: Vec<BlockNode<'tcx>>,
     pub arg_size: usize, 
+    // the types of the locals.
+    pub local_tys: Vec<Ty<'tcx>>,
     // we shrink a SCC into a node and use a father node to represent the SCC.
     pub father_block: Vec<usize>,
     // record the constant value during safedrop checking.
@@ -98,7 +141,61 @@ pub struct SafeDropGraph<'tcx>{
     // record the information of bugs for the function.
     pub bug_records: BugRecords,
     // a threhold to avoid path explosion.
-    pub visit_times: usize
+    pub visit_times: usize,
+    // record the closures and fn items that may be stored in a local, used to resolve indirect calls.
+    pub fn_targets: FxHashMap<usize, FxHashSet<DefId>>,
+    // the head of the innermost loop that contains the block.
+    pub loop_head: Vec<usize>,
+    // the head of the enclosing loop for an inner loop head.
+    pub head_parent: FxHashMap<usize, usize>,
+    // merge the states at join points instead of enumerating the paths.
+    pub join_mode: bool,
+    // the exits of the loops under exploration, indexed by the number of loops still containing the exit block.
+    pub loop_exits: Vec<FxHashMap<usize, Vec<LoopExit>>>,
+    // the blocks visited inside the outermost loop under exploration, a threhold to avoid path explosion.
+    pub loop_visits: usize,
+    // the states already seen at the entry of a block with the loop iterations under exploration, used to prune equivalent paths.
+    pub visited_states: FxHashSet<(usize, StateKey, Vec<(usize, Vec<StateKey>)>)>,
+    // the locals only ever assigned integer constants, their values in constant_bool are the indices of array elements.
+    pub const_locals: FxHashSet<usize>,
+    // the comparisons known on the current path.
+    pub constraint: PathConstraint,
+    // the resume function of a generator, whose state is not a place of the caller.
+    pub is_generator: bool,
+    // the suspensions of a generator by their returns.
+    pub suspensions: FxHashMap<usize, Suspension>,
+    // the states of the paths reaching a suspension.
+    pub suspend_states: Vec<SuspendState>,
+    // the paths after a suspension are being explored, they are not part of the current call.
+    pub resumed: bool,
+}
+
+//a loop under exploration, the inner loops are pushed on top of their enclosing loop.
+#[derive(Debug,Clone)]
+pub struct LoopFrame{
+    pub head: usize,
+    // the number of nodes when entering the loop, the nodes created inside the loop are not compared.
+    pub node_count: usize,
+    // the states at the loop head in previous iterations.
+    pub rounds: Vec<StateKey>,
+}
+
+impl LoopFrame{
+    pub fn new(head: usize, node_count: usize) -> LoopFrame{
+        LoopFrame { head: head, node_count: node_count, rounds: Vec::new() }
+    }
+}
+
+//the state of a path leaving a loop, which is explored once the loop is stable.
+#[derive(Debug,Clone)]
+pub struct LoopExit{
+    pub nodes: Vec<Node>,
+    pub constant_bool: FxHashMap<usize, usize>,
+    pub constraint: PathConstraint,
+    // the loops which still contain the exit block.
+    pub frames: Vec<LoopFrame>,
+    // the exits of a block are distinct states.
+    pub state: StateKey,
 }
 
 impl<'tcx> SafeDropGraph<'tcx>{
@@ -108,11 +205,27 @@ impl<'tcx> SafeDropGraph<'tcx>{
         let arg_size = my_body.arg_count;
         let mut nodes = Vec::<Node>::new();
         let param_env = tcx.param_env(def_id);
+        // the pointers to the storage of a local are tracked even if the local is of a primitive type.
+        let mut address_taken = FxHashSet::<usize>::default();
+        for block in my_body.basic_blocks().iter(){
+            for statement in block.statements.iter(){
+                if let StatementKind::Assign(ref assign) = statement.kind {
+                    match assign.1 {
+                        Rvalue::Ref(_, _, ref p) | Rvalue::AddressOf(_, ref p) => {
+                            if p.is_indirect() == false{
+                                address_taken.insert(p.local.as_usize());
+                            }
+                        },
+                        _ => {},
+                    }
+                }
+            }
+        }
         for ld in 0..locals.len() {
             let temp = Local::from(ld);
             let need_drop = locals[temp].ty.needs_drop(tcx, param_env);
             let so_so = so_so(locals[temp].ty);
-            let mut node = Node::new(ld, ld, need_drop, need_drop || !so_so);
+            let mut node = Node::new(ld, ld, need_drop, need_drop || !so_so || address_taken.contains(&ld));
             node.kind = kind(locals[temp].ty);
             nodes.push(node);
         }
@@ -120,6 +233,11 @@ impl<'tcx> SafeDropGraph<'tcx>{
         let basicblocks = my_body.basic_blocks();
         let mut blocks = Vec::<BlockNode<'tcx>>::new();
         let mut father_block = Vec::<usize>::new();
+        let mut fn_targets = FxHashMap::<usize, FxHashSet<DefId>>::default();
+        let mut constraint = PathConstraint::new();
+        // the locals assigned an integer constant, and the locals which get their values in other ways.
+        let mut const_locals = FxHashSet::<usize>::default();
+        let mut other_defs = address_taken.clone();
         
         // handle each basicblock
         for i in 0..basicblocks.len(){
@@ -133,6 +251,32 @@ impl<'tcx> SafeDropGraph<'tcx>{
                 if let StatementKind::Assign(ref assign) = statement.kind {
                     let left_ssa = assign.0.local.as_usize();
                     let left = assign.0.clone();
+                    if left.projection.is_empty(){
+                        current_node.definitions.push(rvalue_definition(tcx, param_env, locals, left_ssa, &assign.1));
+                        match assign.1{
+                            Rvalue::Use(Operand::Constant(ref constant)) if constant.literal.try_eval_usize(tcx, param_env).is_some() => {
+                                const_locals.insert(left_ssa);
+                            },
+                            _ => {
+                                other_defs.insert(left_ssa);
+                            },
+                        }
+                    }
+                    // the reads through a pointer are checked before the assignment.
+                    for operand in read_operands(&assign.1){
+                        if let Operand::Copy(ref p) | Operand::Move(ref p) = operand{
+                            if p.is_indirect() && nodes[p.local.as_usize()].so_so(){
+                                let read = Assignment::new(p.clone(), p.clone(), 5, statement.source_info.span.clone());
+                                current_node.assignments.push(read);
+                            }
+                        }
+                    }
+                    match assign.1 {
+                        Rvalue::Ref(_, BorrowKind::Mut { .. }, ref p) | Rvalue::AddressOf(Mutability::Mut, ref p) => {
+                            constraint.borrowed.insert(p.local.as_usize());
+                        },
+                        _ => {},
+                    }
                     match assign.1 {
                         Rvalue::Use(ref x) => {
                             match x {
@@ -153,6 +297,14 @@ impl<'tcx> SafeDropGraph<'tcx>{
                                     }
                                 },
                                 Operand::Constant(ref constant) => { 
+                                    // a pointer to a static.
+                                    if let Some(static_id) = constant.check_static_ptr(tcx){
+                                        if nodes[left_ssa].so_so(){
+                                            let assign = Assignment::global(left, static_id, statement.source_info.span.clone());
+                                            current_node.assignments.push(assign);
+                                        }
+                                        continue;
+                                    }
                                     if let None = constant.literal.try_to_scalar(){
                                         continue;
                                     }
@@ -188,23 +340,23 @@ impl<'tcx> SafeDropGraph<'tcx>{
                         },
                         
                         Rvalue::ShallowInitBox(ref x, _) => {
-                            if nodes[left_ssa].sons.contains_key(&0) == false{
+                            if nodes[left_ssa].sons.contains_key(&FieldKey::Field(0)) == false{
                                 let mut node = Node::new(left_ssa, nodes.len(), false, true);
                                 let mut node1 = Node::new(left_ssa, nodes.len() + 1, false, true);
                                 let mut node2 = Node::new(left_ssa, nodes.len() + 2, false, true);
                                 node.alive = nodes[left_ssa].alive;
                                 node1.alive = node.alive;
                                 node2.alive = node.alive;
-                                node.sons.insert(0, node1.local);
-                                node.field_info.push(0);
-                                node1.sons.insert(0, node2.local);
-                                node1.field_info.push(0);
-                                node1.field_info.push(0);
-                                node2.field_info.push(0);
-                                node2.field_info.push(0);
-                                node2.field_info.push(0);
+                                node.sons.insert(FieldKey::Field(0), node1.local);
+                                node.field_info.push(FieldKey::Field(0));
+                                node1.sons.insert(FieldKey::Field(0), node2.local);
+                                node1.field_info.push(FieldKey::Field(0));
+                                node1.field_info.push(FieldKey::Field(0));
+                                node2.field_info.push(FieldKey::Field(0));
+                                node2.field_info.push(FieldKey::Field(0));
+                                node2.field_info.push(FieldKey::Field(0));
                                 node2.kind = 1;
-                                nodes[left_ssa].sons.insert(0, node.local);
+                                nodes[left_ssa].sons.insert(FieldKey::Field(0), node.local);
                                 nodes.push(node);
                                 nodes.push(node1);
                                 nodes.push(node2);
@@ -247,17 +399,33 @@ impl<'tcx> SafeDropGraph<'tcx>{
                                         current_node.assignments.push(assign);
                                     }
                                 },
-                                Operand::Constant(_) => {},
+                                // fn item reified into a fn pointer.
+                                Operand::Constant(ref constant) => {
+                                    if let ty::FnDef(target_id, _) = constant.literal.ty().kind() {
+                                        fn_targets.entry(left_ssa).or_default().insert(*target_id);
+                                    }
+                                },
                             }
                         },
-                        Rvalue::Aggregate(_, ref x) => {
-                            for each_x in x {
+                        Rvalue::Aggregate(ref agg_kind, ref x) => {
+                            // remember the closure body at its creation site.
+                            if let AggregateKind::Closure(closure_id, _) = **agg_kind {
+                                fn_targets.entry(left_ssa).or_default().insert(closure_id);
+                            }
+                            // the place is rebuilt as a whole, the fields of its old value are dropped from the place.
+                            if nodes[left_ssa].so_so(){
+                                let init = Assignment::new(left, left, 6, statement.source_info.span.clone());
+                                current_node.assignments.push(init);
+                            }
+                            // each operand is assigned to its own field of the aggregate.
+                            for (i, each_x) in x.iter().enumerate() {
+                                let field = aggregate_field(tcx, left, agg_kind, i, x.len(), each_x.ty(locals, tcx));
                                 match each_x {
                                     Operand::Copy(ref p) => {
                                         let right_ssa = p.local.as_usize();
                                         if nodes[left_ssa].so_so() && nodes[right_ssa].so_so(){
                                             let right = p.clone();
-                                            let assign = Assignment::new(left, right, 0, statement.source_info.span.clone());
+                                            let assign = Assignment::new(field, right, 0, statement.source_info.span.clone());
                                             current_node.assignments.push(assign);
                                         }
                                     },
@@ -265,13 +433,29 @@ impl<'tcx> SafeDropGraph<'tcx>{
                                         let right_ssa = p.local.as_usize();
                                         if nodes[left_ssa].so_so() && nodes[right_ssa].so_so(){
                                             let right = p.clone();
-                                            let assign = Assignment::new(left, right, 0, statement.source_info.span.clone());
+                                            // the operand is moved into the aggregate, e.g. the upvars captured by value by a closure.
+                                            let assign = Assignment::new(field, right, 1, statement.source_info.span.clone());
                                             current_node.assignments.push(assign);
                                         }
                                     },
                                     Operand::Constant(_) => {},
                                 }
                             }
+                            // remember the variant of the constructed enum.
+                            if let AggregateKind::Adt(adt_id, variant_index, _, _, _) = **agg_kind {
+                                let adt_def = tcx.adt_def(adt_id);
+                                if adt_def.is_enum(){
+                                    let discr = adt_def.discriminant_for_variant(tcx, variant_index).val as usize;
+                                    let variant = Assignment::variant(left, discr, statement.source_info.span.clone());
+                                    current_node.assignments.push(variant);
+                                }
+                            }
+                        },
+                        Rvalue::ThreadLocalRef(static_id) => {
+                            if nodes[left_ssa].so_so(){
+                                let assign = Assignment::global(left, static_id, statement.source_info.span.clone());
+                                current_node.assignments.push(assign);
+                            }
                         },
                         Rvalue::Discriminant(ref p) => {
                             let right = p.clone();
@@ -281,6 +465,22 @@ impl<'tcx> SafeDropGraph<'tcx>{
                         _ => {}
                     }
                 }
+                if let StatementKind::StorageDead(local) = statement.kind {
+                    if nodes[local.as_usize()].so_so(){
+                        let place = Place::from(local);
+                        let dead = Assignment::new(place, place, 4, statement.source_info.span.clone());
+                        current_node.assignments.push(dead);
+                    }
+                }
+                if let StatementKind::SetDiscriminant { ref place, variant_index } = statement.kind {
+                    if let ty::Adt(adt_def, _) = place.ty(locals, tcx).ty.kind() {
+                        if adt_def.is_enum(){
+                            let discr = adt_def.discriminant_for_variant(tcx, variant_index).val as usize;
+                            let variant = Assignment::variant(**place, discr, statement.source_info.span.clone());
+                            current_node.assignments.push(variant);
+                        }
+                    }
+                }
             }
 
             // handle terminator statements
@@ -288,7 +488,11 @@ impl<'tcx> SafeDropGraph<'tcx>{
                 TerminatorKind::Goto { ref target } => {
                     current_node.push(target.as_usize());
                 },
-                TerminatorKind::SwitchInt{ discr: _, switch_ty: _, ref targets } => {
+                TerminatorKind::SwitchInt{ ref discr, switch_ty: _, ref targets } => {
+                    // the values of a switchInt discriminant in constant_bool come from the taken arms.
+                    if let Some(place) = discr.place(){
+                        other_defs.insert(place.local.as_usize());
+                    }
                     current_node.switch_stmts.push(terminator.clone());
                     for (_, ref target) in targets.iter() {
                         current_node.push(target.as_usize());
@@ -309,11 +513,16 @@ impl<'tcx> SafeDropGraph<'tcx>{
                 },
                 TerminatorKind::DropAndReplace { place: _, value: _, ref target, ref unwind } => {
                     current_node.push(target.as_usize());
+                    current_node.drops.push(terminator.clone());
                     if let Some(target) = unwind {
                         current_node.push(target.as_usize());
                     }
                 },
-                TerminatorKind::Call { func: _, args: _, destination: _, ref target, ref cleanup, from_hir_call: _, fn_span: _ } => {
+                TerminatorKind::Call { ref func, ref args, ref destination, ref target, ref cleanup, from_hir_call: _, fn_span: _ } => {
+                    if destination.projection.is_empty(){
+                        current_node.definitions.push(call_definition(tcx, func, args, destination.local.as_usize()));
+                    }
+                    other_defs.insert(destination.local.as_usize());
                     if let Some(tt) = target {
                         current_node.push(tt.as_usize());
                     }
@@ -351,6 +560,12 @@ impl<'tcx> SafeDropGraph<'tcx>{
             }
             blocks.push(current_node);
         }
+        // a suspended generator is resumed or cancelled later.
+        let is_generator = my_body.generator_kind().is_some();
+        let mut suspensions = FxHashMap::default();
+        if is_generator{
+            suspensions = generator_blocks(my_body, tcx, &mut blocks, &mut father_block);
+        }
 
         SafeDropGraph{
             def_id: def_id.clone(),
@@ -358,13 +573,27 @@ impl<'tcx> SafeDropGraph<'tcx>{
             blocks: blocks,
             nodes: nodes,
             arg_size: arg_size,
+            local_tys: locals.iter().map(|ld| ld.ty).collect(),
             father_block: father_block,
             constant_bool: FxHashMap::default(), 
+            const_locals: const_locals.difference(&other_defs).cloned().collect(),
             count: 0,
             return_results: ReturnResults::new(arg_size),
             return_set: FxHashSet::default(),
             bug_records: BugRecords::new(),
             visit_times: 0,
+            fn_targets: fn_targets,
+            loop_head: (0..blocks.len()).collect(),
+            head_parent: FxHashMap::default(),
+            join_mode: false,
+            loop_exits: Vec::new(),
+            loop_visits: 0,
+            visited_states: FxHashSet::default(),
+            constraint: constraint,
+            is_generator: is_generator,
+            suspensions: suspensions,
+            suspend_states: Vec::new(),
+            resumed: false,
         }
     }
 
@@ -428,5 +657,142 @@ impl<'tcx> SafeDropGraph<'tcx>{
         let mut dfn = vec![0 as usize; self.blocks.len()];
         let mut low = vec![0 as usize; self.blocks.len()];
         self.tarjan(0, &mut stack, &mut instack, &mut dfn, &mut low);
+        for i in 0..self.blocks.len(){
+            if self.father_block[i] == i && self.blocks[i].sub_blocks.len() > 0{
+                let mut members = self.blocks[i].sub_blocks.clone();
+                members.push(i);
+                self.nest_loops(i, members);
+            }
+        }
+    }
+
+    // build the loop-nesting forest: the loop without its head is split into SCCs again,
+    // and each of them is an inner loop nested in the current one.
+    pub fn nest_loops(&mut self, head: usize, members: Vec<usize>){
+        for i in members.iter(){
+            self.loop_head[*i] = head;
+        }
+        let body: FxHashSet<usize> = members.iter().cloned().filter(|i| *i != head).collect();
+        for scc in sub_scc(&self.blocks, &body){
+            if scc.len() == 1 && self.blocks[scc[0]].cfg_next.contains(&scc[0]) == false{
+                continue;
+            }
+            // the head of the inner loop is the block entered from outside the inner loop.
+            let scc_set: FxHashSet<usize> = scc.iter().cloned().collect();
+            let mut inner_head = scc[0];
+            for i in scc.iter(){
+                let entered = self.blocks.iter().enumerate().any(|(j, block)| {
+                    scc_set.contains(&j) == false && block.cfg_next.contains(i)
+                });
+                if entered{
+                    inner_head = *i;
+                    break;
+                }
+            }
+            self.head_parent.insert(inner_head, head);
+            self.nest_loops(inner_head, scc);
+        }
+    }
+
+    // whether the block belongs to the loop with the given head, including its inner loops.
+    pub fn in_loop(&self, bb_index: usize, head: usize) -> bool{
+        let mut current = self.loop_head[bb_index];
+        loop{
+            if current == head{
+                return true;
+            }
+            match self.head_parent.get(&current){
+                Some(parent) => current = *parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+//the field of an aggregate which holds the i-th operand: the element of an array, the field of
+//the constructed variant of an ADT, or the field of a tuple, a closure or a generator (the upvars).
+pub fn aggregate_field<'tcx>(tcx: TyCtxt<'tcx>, place: Place<'tcx>, agg_kind: &AggregateKind<'tcx>, i: usize, len: usize, ty: Ty<'tcx>) -> Place<'tcx>{
+    match *agg_kind{
+        AggregateKind::Array(_) => {
+            let elem = ProjectionElem::ConstantIndex { offset: i as u64, min_length: len as u64, from_end: false };
+            return tcx.mk_place_elem(place, elem);
+        },
+        AggregateKind::Adt(adt_id, variant_index, _, _, active_field) => {
+            let adt_def = tcx.adt_def(adt_id);
+            // a union has only one active field.
+            let field = Field::from_usize(active_field.unwrap_or(i));
+            if adt_def.is_enum(){
+                let downcast = tcx.mk_place_downcast(place, adt_def, variant_index);
+                return tcx.mk_place_field(downcast, field, ty);
+            }
+            return tcx.mk_place_field(place, field, ty);
+        },
+        _ => {
+            return tcx.mk_place_field(place, Field::from_usize(i), ty);
+        },
+    }
+}
+
+//tarjan algorithm on the subgraph induced by the given blocks.
+pub fn sub_scc<'tcx>(blocks: &Vec<BlockNode<'tcx>>, members: &FxHashSet<usize>) -> Vec<Vec<usize>>{
+    let mut sccs = Vec::new();
+    let mut stack = Vec::new();
+    let mut dfn = FxHashMap::default();
+    let mut low = FxHashMap::default();
+    let mut count = 0;
+    let mut order: Vec<usize> = members.iter().cloned().collect();
+    order.sort();
+    for i in order{
+        if dfn.contains_key(&i) == false{
+            sub_tarjan(i, blocks, members, &mut stack, &mut dfn, &mut low, &mut count, &mut sccs);
+        }
+    }
+    return sccs;
+}
+
+fn sub_tarjan<'tcx>(index: usize, blocks: &Vec<BlockNode<'tcx>>, members: &FxHashSet<usize>,
+    stack: &mut Vec<usize>,
+    dfn: &mut FxHashMap<usize, usize>,
+    low: &mut FxHashMap<usize, usize>,
+    count: &mut usize,
+    sccs: &mut Vec<Vec<usize>>){
+    dfn.insert(index, *count);
+    low.insert(index, *count);
+    *count += 1;
+    stack.push(index);
+    for target in blocks[index].cfg_next.iter(){
+        if members.contains(target) == false{
+            continue;
+        }
+        if dfn.contains_key(target) == false{
+            sub_tarjan(*target, blocks, members, stack, dfn, low, count, sccs);
+            low.insert(index, min(low[&index], low[target]));
+        }
+        else if stack.contains(target){
+            low.insert(index, min(low[&index], dfn[target]));
+        }
+    }
+    // generate SCC
+    if dfn[&index] == low[&index]{
+        let mut scc = Vec::new();
+        loop{
+            let top = stack.pop().unwrap();
+            scc.push(top);
+            if top == index{
+                break;
+            }
+        }
+        sccs.push(scc);
+    }
+}
+
+//the operands read by the rvalue.
+pub fn read_operands<'a, 'tcx>(rvalue: &'a Rvalue<'tcx>) -> Vec<&'a Operand<'tcx>>{
+    match rvalue{
+        Rvalue::Use(ref x) | Rvalue::Repeat(ref x, _) | Rvalue::Cast(_, ref x, _)
+        | Rvalue::UnaryOp(_, ref x) | Rvalue::ShallowInitBox(ref x, _) => vec![x],
+        Rvalue::BinaryOp(_, ref operands) | Rvalue::CheckedBinaryOp(_, ref operands) => vec![&operands.0, &operands.1],
+        Rvalue::Aggregate(_, ref x) => x.iter().collect(),
+        _ => Vec::new(),
     }
 }
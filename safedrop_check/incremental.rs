@@ -0,0 +1,363 @@
+use std::fs;
+use std::hash::Hasher;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use rustc_middle::mir::Operand;
+use rustc_middle::mir::terminator::TerminatorKind;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::def_id::DefId;
+use rustc_span::def_id::LOCAL_CRATE;
+use rustc_data_structures::fingerprint::Fingerprint;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::fx::FxHashSet;
+use rustc_data_structures::fx::FxHasher;
+use rustc_data_structures::stable_hasher::HashStable;
+use rustc_data_structures::stable_hasher::StableHasher;
+use super::SafeDropGraph;
+use super::node::FieldKey;
+use super::node::ReturnAssign;
+use super::node::ReturnResults;
+use super::tools::FuncMap;
+
+
+//the analysis result of a function persisted across builds.
+#[derive(Clone)]
+pub struct CacheEntry{
+    pub mir_hash: String,
+    pub summary: ReturnResults,
+    // the callees resolved in the function and the hash of their summaries, None if no summary was used.
+    pub deps: Vec<(String, Option<u64>)>,
+    // the bugs found in the function, None if it was only analyzed as a callee.
+    pub message: Option<String>,
+}
+
+struct AnalysisCache{
+    path: PathBuf,
+    entries: FxHashMap<String, CacheEntry>,
+    // the entries used or updated in the current build, only these are saved.
+    touched: FxHashSet<String>,
+    // the MIR hashes and the callees of the functions, computed once in the current build.
+    mir_hashes: FxHashMap<DefId, String>,
+    deps: FxHashMap<DefId, Vec<DefId>>,
+}
+
+static ANALYSIS_CACHE: Mutex<Option<AnalysisCache>> = Mutex::new(None);
+
+fn with_cache<'tcx, R>(tcx: TyCtxt<'tcx>, f: impl FnOnce(&mut AnalysisCache) -> R) -> R{
+    let mut guard = ANALYSIS_CACHE.lock().unwrap();
+    let cache = guard.get_or_insert_with(|| {
+        // the crates of the same name, e.g. the lib and the bin of a package, have their own caches.
+        let crate_types: Vec<String> = tcx.sess.crate_types().iter().map(|crate_type| crate_type.to_string()).collect();
+        let path = tcx.output_filenames(()).out_directory
+            .join("safedrop")
+            .join(format!("{}-{:016x}-{}.cache", tcx.crate_name(LOCAL_CRATE), tcx.sess.local_stable_crate_id().to_u64(), crate_types.join("+")));
+        let entries = match fs::read_to_string(&path){
+            Ok(text) => decode_cache(&text),
+            Err(_) => FxHashMap::default(),
+        };
+        AnalysisCache { path: path, entries: entries, touched: FxHashSet::default(), mir_hashes: FxHashMap::default(), deps: FxHashMap::default() }
+    });
+    return f(cache);
+}
+
+//the stable key of a function across builds.
+pub fn func_key<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> String{
+    return tcx.def_path_hash(def_id).0.to_hex();
+}
+
+//the hash of the MIR of a function, including the spans used in bug reports.
+pub fn mir_hash<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> String{
+    if let Some(hash) = with_cache(tcx, |cache| cache.mir_hashes.get(&def_id).cloned()){
+        return hash;
+    }
+    let body = tcx.optimized_mir(def_id);
+    let mut hcx = tcx.create_stable_hashing_context();
+    let mut hasher = StableHasher::new();
+    body.hash_stable(&mut hcx, &mut hasher);
+    let fingerprint: Fingerprint = hasher.finish();
+    let hash = fingerprint.to_hex();
+    with_cache(tcx, |cache| cache.mir_hashes.insert(def_id, hash.clone()));
+    return hash;
+}
+
+//the functions whose summaries may be used when analyzing the function, see summary_deps.
+pub fn func_deps<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> Vec<DefId>{
+    if let Some(deps) = with_cache(tcx, |cache| cache.deps.get(&def_id).cloned()){
+        return deps;
+    }
+    let body = tcx.optimized_mir(def_id);
+    let mut safedrop_graph = SafeDropGraph::new(&body, tcx, def_id);
+    return safedrop_graph.summary_deps(tcx);
+}
+
+pub fn summary_hash(summary: &ReturnResults) -> u64{
+    let mut hasher = FxHasher::default();
+    hasher.write(encode_summary(summary).as_bytes());
+    return hasher.finish();
+}
+
+impl<'tcx> SafeDropGraph<'tcx>{
+    //the functions whose summaries may be used when analyzing this function.
+    pub fn summary_deps(&mut self, tcx: TyCtxt<'tcx>) -> Vec<DefId>{
+        let mut deps = Vec::new();
+        for block in self.blocks.clone(){
+            for call in block.calls{
+                if let TerminatorKind::Call { ref func, .. } = call.kind {
+                    if let Operand::Constant(_) = func {
+                        deps.extend(self.call_targets(func, &vec![0], tcx));
+                    }
+                    else{
+                        for targets in self.fn_targets.values(){
+                            deps.extend(targets.iter().cloned());
+                        }
+                    }
+                }
+            }
+        }
+        deps.sort();
+        deps.dedup();
+        with_cache(tcx, |cache| cache.deps.insert(self.def_id, deps.clone()));
+        return deps;
+    }
+}
+
+//reuse the result of the last build if the function and the summaries of its callees are unchanged.
+pub fn cached_analysis<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId, func_map: &mut FuncMap, need_message: bool) -> Option<CacheEntry>{
+    let key = func_key(tcx, def_id);
+    let entry = with_cache(tcx, |cache| cache.entries.get(&key).cloned())?;
+    if need_message && entry.message.is_none(){
+        return None;
+    }
+    if entry.mir_hash != mir_hash(tcx, def_id){
+        return None;
+    }
+    let deps = func_deps(tcx, def_id);
+    // a callee resolved now but not in the last build.
+    if deps.len() != entry.deps.len(){
+        return None;
+    }
+    // the graph is only built to fetch the summaries of the callees.
+    let mut safedrop_graph = None;
+    for dep in deps{
+        let dep_key = func_key(tcx, dep);
+        let recorded = entry.deps.iter().find(|d| d.0 == dep_key)?;
+        // the callee has no summary, or its summary was not used in the last build.
+        let hash = match recorded.1{
+            Some(hash) => hash,
+            None => continue,
+        };
+        let graph = safedrop_graph.get_or_insert_with(|| SafeDropGraph::new(tcx.optimized_mir(def_id), tcx, def_id));
+        match graph.callee_summary(dep, tcx, func_map){
+            Some(summary) if summary_hash(&summary) == hash => {},
+            _ => return None,
+        }
+    }
+    with_cache(tcx, |cache| cache.touched.insert(key));
+    return Some(entry);
+}
+
+//the resolved callees of a function, with the hash of the summary used for each of them.
+pub fn dep_hashes<'tcx>(tcx: TyCtxt<'tcx>, deps: &Vec<DefId>, func_map: &FuncMap) -> Vec<(String, Option<u64>)>{
+    return deps.iter().map(|dep| (func_key(tcx, *dep), func_map.map.get(dep).map(|summary| summary_hash(summary)))).collect();
+}
+
+//store the result of the function for the next build.
+pub fn record_analysis<'tcx>(tcx: TyCtxt<'tcx>, safedrop_graph: &mut SafeDropGraph<'tcx>, func_map: &FuncMap, message: Option<String>){
+    let deps = safedrop_graph.summary_deps(tcx);
+    let entry = CacheEntry{
+        mir_hash: mir_hash(tcx, safedrop_graph.def_id),
+        summary: safedrop_graph.return_results.clone(),
+        deps: dep_hashes(tcx, &deps, func_map),
+        message: message,
+    };
+    store_entry(tcx, safedrop_graph.def_id, entry);
+}
+
+//store the summaries of a converged component, analyzed as callees with the given callees of their own.
+pub fn record_component<'tcx>(tcx: TyCtxt<'tcx>, members: &Vec<DefId>, func_map: &mut FuncMap){
+    for member in members.iter(){
+        let deps = match func_map.pending.remove(member){
+            Some(deps) => deps,
+            None => continue,
+        };
+        let entry = CacheEntry{
+            mir_hash: mir_hash(tcx, *member),
+            summary: func_map.map[member].clone(),
+            deps: dep_hashes(tcx, &deps, func_map),
+            message: None,
+        };
+        store_entry(tcx, *member, entry);
+    }
+}
+
+fn store_entry<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId, mut entry: CacheEntry){
+    // the statics of a summary are not written to the cache, they can not be found again in the next build.
+    if entry.summary.globals.is_empty() == false{
+        return;
+    }
+    let key = func_key(tcx, def_id);
+    with_cache(tcx, |cache| {
+        // the bugs found when the function was checked itself are still valid for the same MIR and callees.
+        if let Some(old) = cache.entries.get(&key){
+            if entry.message.is_none() && old.mir_hash == entry.mir_hash && old.deps == entry.deps{
+                entry.message = old.message.clone();
+            }
+        }
+        cache.touched.insert(key.clone());
+        cache.entries.insert(key, entry);
+    });
+}
+
+//write the cache into the target directory, called after all the functions are checked.
+pub fn save_cache<'tcx>(tcx: TyCtxt<'tcx>){
+    with_cache(tcx, |cache| {
+        let mut text = String::new();
+        for (key, entry) in cache.entries.iter(){
+            if cache.touched.contains(key){
+                text.push_str(&encode_entry(key, entry));
+            }
+        }
+        if let Some(dir) = cache.path.parent(){
+            let _ = fs::create_dir_all(dir);
+        }
+        let _ = fs::write(&cache.path, text);
+    });
+}
+
+//a field path is written as keys joined by '.': `3` a field, `1:3` a field of variant 1, `e5` an element, `e_` any element.
+fn encode_fields(fields: &Vec<FieldKey>) -> String{
+    if fields.is_empty(){
+        return "-".to_string();
+    }
+    return fields.iter().map(|f| match f{
+        FieldKey::Field(field) => field.to_string(),
+        FieldKey::VariantField(variant, field) => format!("{}:{}", variant, field),
+        FieldKey::Element(offset) => format!("e{}", offset),
+        FieldKey::AnyElement => "e_".to_string(),
+    }).collect::<Vec<String>>().join(".");
+}
+
+fn decode_fields(text: &str) -> Option<Vec<FieldKey>>{
+    if text == "-"{
+        return Some(Vec::new());
+    }
+    return text.split('.').map(|f| {
+        if f == "e_"{
+            return Some(FieldKey::AnyElement);
+        }
+        if let Some(offset) = f.strip_prefix('e'){
+            return offset.parse().ok().map(FieldKey::Element);
+        }
+        match f.split_once(':'){
+            Some((variant, field)) => Some(FieldKey::VariantField(variant.parse().ok()?, field.parse().ok()?)),
+            None => f.parse().ok().map(FieldKey::Field),
+        }
+    }).collect();
+}
+
+fn encode_summary(summary: &ReturnResults) -> String{
+    let mut text = format!("sum {}\n", summary.arg_size);
+    let mut dead: Vec<usize> = summary.dead.iter().cloned().collect();
+    dead.sort();
+    for d in dead{
+        text.push_str(&format!("dead {}\n", d));
+    }
+    let mut assigns = Vec::new();
+    for assign in summary.assignments.iter(){
+        assigns.push(format!("assign {} {} {} {} {} {} {} {} {}\n", assign.atype,
+            assign.left_index, encode_fields(&assign.left), assign.left_so_so as usize, assign.left_need_drop as usize,
+            assign.right_index, encode_fields(&assign.right), assign.right_so_so as usize, assign.right_need_drop as usize));
+    }
+    assigns.sort();
+    for assign in assigns{
+        text.push_str(&assign);
+    }
+    return text;
+}
+
+fn encode_entry(key: &String, entry: &CacheEntry) -> String{
+    let mut text = format!("fn {} {}\n", key, entry.mir_hash);
+    text.push_str(&encode_summary(&entry.summary));
+    for dep in entry.deps.iter(){
+        match dep.1{
+            Some(hash) => text.push_str(&format!("dep {} {}\n", dep.0, hash)),
+            None => text.push_str(&format!("dep {} -\n", dep.0)),
+        }
+    }
+    if let Some(message) = &entry.message{
+        text.push_str("message\n");
+        for line in message.lines(){
+            text.push_str(&format!("msg {}\n", line));
+        }
+    }
+    text.push_str("end\n");
+    return text;
+}
+
+//parse the cache file, a malformed entry is dropped.
+fn decode_cache(text: &str) -> FxHashMap<String, CacheEntry>{
+    let mut entries = FxHashMap::default();
+    let mut current: Option<(String, CacheEntry)> = None;
+    let mut valid = true;
+    for line in text.lines(){
+        let (tag, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let tokens: Vec<&str> = rest.split(' ').collect();
+        match tag{
+            "fn" if tokens.len() == 2 => {
+                let entry = CacheEntry{
+                    mir_hash: tokens[1].to_string(),
+                    summary: ReturnResults::new(0),
+                    deps: Vec::new(),
+                    message: None,
+                };
+                current = Some((tokens[0].to_string(), entry));
+                valid = true;
+            },
+            "end" => {
+                if let Some((key, entry)) = current.take(){
+                    if valid{
+                        entries.insert(key, entry);
+                    }
+                }
+            },
+            _ => {
+                let entry = match current.as_mut(){
+                    Some((_, entry)) => entry,
+                    None => continue,
+                };
+                let ok = match tag{
+                    "sum" => rest.parse().map(|arg_size| entry.summary.arg_size = arg_size).is_ok(),
+                    "dead" => rest.parse().map(|dead| { entry.summary.dead.insert(dead); }).is_ok(),
+                    "assign" if tokens.len() == 9 => {
+                        match decode_assign(&tokens){
+                            Some(assign) => { entry.summary.assignments.push(assign); true },
+                            None => false,
+                        }
+                    },
+                    "dep" if tokens.len() == 2 && tokens[1] == "-" => { entry.deps.push((tokens[0].to_string(), None)); true },
+                    "dep" if tokens.len() == 2 => tokens[1].parse().map(|hash| entry.deps.push((tokens[0].to_string(), Some(hash)))).is_ok(),
+                    "message" => { entry.message = Some(String::new()); true },
+                    "msg" => {
+                        match entry.message.as_mut(){
+                            Some(message) => { message.push_str(rest); message.push('\n'); true },
+                            None => false,
+                        }
+                    },
+                    _ => false,
+                };
+                valid = valid && ok;
+            },
+        }
+    }
+    return entries;
+}
+
+fn decode_assign(tokens: &Vec<&str>) -> Option<ReturnAssign>{
+    let flag = |t: &str| -> Option<bool> { t.parse::<usize>().ok().map(|f| f == 1) };
+    let mut assign = ReturnAssign::new(tokens[0].parse().ok()?,
+        tokens[1].parse().ok()?, flag(tokens[3])?, flag(tokens[4])?,
+        tokens[5].parse().ok()?, flag(tokens[7])?, flag(tokens[8])?);
+    assign.left = decode_fields(tokens[2])?;
+    assign.right = decode_fields(tokens[6])?;
+    return Some(assign);
+}
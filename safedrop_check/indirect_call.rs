@@ -0,0 +1,214 @@
+use rustc_middle::mir::Operand;
+use rustc_middle::mir::Field;
+use rustc_middle::ty;
+use rustc_middle::ty::Instance;
+use rustc_middle::ty::InstanceDef;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::def_id::DefId;
+use rustc_data_structures::fx::FxHashSet;
+use super::SafeDropGraph;
+use super::node::ReturnResults;
+
+
+impl<'tcx> SafeDropGraph<'tcx>{
+    //approximate the possible callees of a call terminator.
+    //direct calls have one target; calls through fn pointers, closures and trait objects may have several.
+    pub fn call_targets(&mut self, func: &Operand<'tcx>, merge_vec: &Vec<usize>, tcx: TyCtxt<'tcx>) -> Vec<DefId>{
+        match func {
+            Operand::Constant(ref constant) => {
+                if let ty::FnDef(target_id, substs) = constant.literal.ty().kind() {
+                    if tcx.trait_of_item(*target_id).is_none(){
+                        return vec![*target_id];
+                    }
+                    let param_env = tcx.param_env(self.def_id);
+                    match Instance::resolve(tcx, param_env, *target_id, substs) {
+                        Ok(Some(instance)) => {
+                            match instance.def {
+                                InstanceDef::Item(_) => return vec![instance.def_id()],
+                                InstanceDef::Virtual(..) => return self.dyn_targets(*target_id, merge_vec, tcx),
+                                // FnOnce::call_once on a closure implementing Fn or FnMut.
+                                InstanceDef::ClosureOnceShim { .. } => {
+                                    if let ty::Closure(closure_id, _) = substs.type_at(0).kind() {
+                                        return vec![*closure_id];
+                                    }
+                                },
+                                _ => {},
+                            }
+                        },
+                        _ => {},
+                    }
+                    return vec![*target_id];
+                }
+                return Vec::new();
+            },
+            // fn pointers and closures stored in a local.
+            Operand::Copy(ref p) | Operand::Move(ref p) => {
+                let callee = self.handle_projection(true, p.local.as_usize(), tcx, p.clone());
+                return self.stored_targets(callee).into_iter().collect();
+            },
+        }
+    }
+
+    //collect the closures and fn items that may flow into the node through the alias graph.
+    //only the node and its aliases are visited, a fn pointer or a closure in a field is not the node itself.
+    pub fn stored_targets(&self, node: usize) -> FxHashSet<DefId>{
+        let mut targets = FxHashSet::default();
+        let mut record = FxHashSet::default();
+        let mut worklist = vec![node];
+        while let Some(current) = worklist.pop(){
+            if record.insert(current) == false{
+                continue;
+            }
+            if let Some(fns) = self.fn_targets.get(&self.nodes[current].index){
+                targets.extend(fns.iter().cloned());
+            }
+            for i in self.nodes[current].alias.iter(){
+                worklist.push(*i);
+            }
+        }
+        return targets;
+    }
+
+    //the nodes passed to each parameter of the target, the return place first.
+    //the closure body takes the environment and the untupled arguments, while Fn::call, FnMut::call_mut
+    //and FnOnce::call_once take the closure (or a reference to it) and a tuple of the arguments.
+    //the environment is passed as the trait method gets it: a reference for call and call_mut, the closure
+    //itself for call_once on a FnOnce closure. the call_once shim of a Fn or FnMut closure passes a reference
+    //to the closure it owns. a closure called through a fn pointer captures nothing, its environment is empty.
+    pub fn target_args(&mut self, target_id: DefId, merge_vec: &Vec<usize>, func: &Operand<'tcx>, args: &Vec<Operand<'tcx>>, tcx: TyCtxt<'tcx>) -> Vec<usize>{
+        if tcx.is_closure(target_id) == false{
+            return merge_vec.clone();
+        }
+        let mut fn_trait = false;
+        let mut shim = false;
+        if let Operand::Constant(ref constant) = func{
+            if let ty::FnDef(method_id, substs) = constant.literal.ty().kind(){
+                if let Some(trait_id) = tcx.trait_of_item(*method_id){
+                    fn_trait = tcx.fn_trait_kind_from_lang_item(trait_id).is_some();
+                }
+                let param_env = tcx.param_env(self.def_id);
+                if let Ok(Some(instance)) = Instance::resolve(tcx, param_env, *method_id, substs){
+                    shim = matches!(instance.def, InstanceDef::ClosureOnceShim { .. });
+                }
+            }
+        }
+        // a fn pointer coerced from the closure.
+        if fn_trait == false{
+            let mut closure_vec = vec![merge_vec[0], 0];
+            closure_vec.extend(merge_vec[1..].iter().cloned());
+            return closure_vec;
+        }
+        if merge_vec.len() != 3 || args.len() != 2{
+            return merge_vec.clone();
+        }
+        let mut closure_vec = vec![merge_vec[0]];
+        let env = merge_vec[1];
+        if shim{
+            closure_vec.push(self.ref_node(env));
+        }
+        else{
+            closure_vec.push(env);
+        }
+        match args[1] {
+            Operand::Copy(ref p) | Operand::Move(ref p) => {
+                if p.projection.is_empty() == false{
+                    return merge_vec.clone();
+                }
+                if let ty::Tuple(tys) = self.local_tys[p.local.as_usize()].kind() {
+                    for (i, field_ty) in tys.iter().enumerate(){
+                        let field = tcx.mk_place_field(*p, Field::from_usize(i), field_ty);
+                        let node = self.handle_projection(true, p.local.as_usize(), tcx, field);
+                        closure_vec.push(node);
+                    }
+                }
+            },
+            // a closure without arguments.
+            Operand::Constant(_) => {},
+        }
+        return closure_vec;
+    }
+
+    //resolve a virtual call: first look at what the receiver may hold (e.g. a boxed closure),
+    //otherwise fall back to the implementations of the trait in the current crate.
+    pub fn dyn_targets(&self, method_id: DefId, merge_vec: &Vec<usize>, tcx: TyCtxt<'tcx>) -> Vec<DefId>{
+        if merge_vec.len() > 1{
+            let stored = self.stored_targets(merge_vec[1]);
+            if stored.is_empty() == false{
+                return stored.into_iter().collect();
+            }
+        }
+        let mut targets = Vec::new();
+        if let Some(trait_id) = tcx.trait_of_item(method_id){
+            for impl_id in tcx.all_impls(trait_id){
+                if impl_id.is_local() == false{
+                    continue;
+                }
+                if let Some(item_id) = tcx.impl_item_implementor_ids(impl_id).get(&method_id){
+                    targets.push(*item_id);
+                }
+            }
+        }
+        return targets;
+    }
+}
+
+//merge the summaries of all candidate callees of an indirect call, target_vecs holds the nodes passed to the
+//parameters of each candidate. the summaries are first numbered by the nodes of the call site, the union of
+//these nodes are the arguments of the merged summary and its statics are numbered after them.
+//alias relations of any candidate may hold, but only frees performed by every candidate are kept.
+//return the merged summary and the nodes of its arguments.
+pub fn merge_summaries(summaries: &Vec<ReturnResults>, target_vecs: &Vec<Vec<usize>>) -> (ReturnResults, Vec<usize>){
+    let mut slots = Vec::new();
+    for target_vec in target_vecs.iter(){
+        for node in target_vec.iter(){
+            if slots.contains(node) == false{
+                slots.push(*node);
+            }
+        }
+    }
+    let mut merged = ReturnResults::new(slots.len().saturating_sub(1));
+    for summary in summaries.iter(){
+        for static_id in summary.globals.iter(){
+            if merged.globals.contains(static_id) == false{
+                merged.globals.push(*static_id);
+            }
+        }
+    }
+    let mut assign_set = FxHashSet::default();
+    for (i, summary) in summaries.iter().enumerate(){
+        let target_vec = &target_vecs[i];
+        // a parameter the call site does not pass has no number.
+        let renumber = |index: usize| -> Option<usize> {
+            if index <= summary.arg_size{
+                let node = target_vec.get(index)?;
+                return slots.iter().position(|slot| slot == node);
+            }
+            let static_id = summary.globals[index - summary.arg_size - 1];
+            return Some(merged.arg_size + 1 + merged.globals.iter().position(|g| *g == static_id).unwrap());
+        };
+        let mut assignments = Vec::new();
+        for assign in summary.assignments.iter(){
+            let mut assign = assign.clone();
+            match (renumber(assign.left_index), renumber(assign.right_index)){
+                (Some(left_index), Some(right_index)) => {
+                    assign.left_index = left_index;
+                    assign.right_index = right_index;
+                },
+                _ => continue,
+            }
+            let key = (assign.left_index, assign.left.clone(), assign.right_index, assign.right.clone());
+            if assign_set.insert(key){
+                assignments.push(assign);
+            }
+        }
+        let dead: FxHashSet<usize> = summary.dead.iter().filter_map(|dead| renumber(*dead)).collect();
+        merged.assignments.extend(assignments);
+        if i == 0{
+            merged.dead = dead;
+        }
+        else{
+            merged.dead.retain(|d| dead.contains(d));
+        }
+    }
+    return (merged, slots);
+}
@@ -0,0 +1,261 @@
+use std::collections::VecDeque;
+use std::env;
+use rustc_middle::ty::TyCtxt;
+use rustc_span::def_id::DefId;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_data_structures::fx::FxHashSet;
+use super::SafeDropGraph;
+use super::node::Node;
+use super::node::FieldKey;
+use super::node::ReturnResults;
+use super::constraint::PathConstraint;
+use super::tools::*;
+
+
+impl<'tcx> SafeDropGraph<'tcx>{
+    //run the analysis from the entry block. the path-sensitive mode is used by default, and the join-based mode
+    //is used for the functions selected by SAFEDROP_JOIN or when the path-sensitive mode runs out of budget.
+    pub fn analyze(&mut self, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap){
+        if join_selected(tcx, self.def_id){
+            self.join_mode = true;
+        }
+        if self.join_mode == false{
+            let init_nodes = self.nodes.clone();
+            self.safedrop_check(0, tcx, func_map);
+            if self.visit_times > 10000{
+                // the results of a partial path exploration are dropped.
+                self.nodes = init_nodes;
+                self.constant_bool = FxHashMap::default();
+                self.constraint = PathConstraint { borrowed: self.constraint.borrowed.clone(), ..PathConstraint::new() };
+                self.bug_records = BugRecords::new();
+                self.return_results = ReturnResults::new(self.arg_size);
+                self.return_set = FxHashSet::default();
+                self.suspend_states = Vec::new();
+                self.visit_times = 0;
+                self.join_mode = true;
+            }
+        }
+        if self.join_mode{
+            self.join_check(tcx, func_map);
+        }
+        self.suspend_check(tcx, func_map);
+    }
+
+    //a suspended generator is cancelled or resumed with the state it was suspended in. both continue from
+    //the state of each path reaching a suspension, and only report bugs: they are not effects of the current
+    //call of the resume function. the suspensions reached after a resumption are not followed again.
+    pub fn suspend_check(&mut self, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap){
+        let states = std::mem::take(&mut self.suspend_states);
+        let mut explored = FxHashSet::default();
+        self.resumed = true;
+        for state in states{
+            if explored.insert((state.block, state_key(&state.nodes, &state.constant_bool, &state.constraint))) == false{
+                continue;
+            }
+            let suspension = self.suspensions[&state.block].clone();
+            let mut entries = vec![suspension.cancel];
+            entries.extend(suspension.resume);
+            for entry in entries{
+                self.nodes = state.nodes.clone();
+                self.constant_bool = state.constant_bool.clone();
+                self.constraint = state.constraint.clone();
+                self.visit_times = 0;
+                self.safedrop_check(entry, tcx, func_map);
+            }
+        }
+        self.resumed = false;
+    }
+
+    //the join-based mode: a worklist analysis over the CFG which merges the states at join points
+    //instead of enumerating the paths. it always terminates at the cost of some precision.
+    pub fn join_check(&mut self, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap){
+        // the nodes of the locals, which are the same in all the states.
+        let base = self.nodes.len();
+        let block_count = self.blocks.len();
+        let mut in_states: Vec<Option<(Vec<Node>, FxHashMap<usize, usize>, PathConstraint)>> = vec![None; block_count];
+        let mut visits = vec![0 as usize; block_count];
+        in_states[0] = Some((self.nodes.clone(), self.constant_bool.clone(), self.constraint.clone()));
+        let mut worklist = VecDeque::new();
+        worklist.push_back(0);
+        while let Some(bb_index) = worklist.pop_front(){
+            if visits[bb_index] >= MAX_JOIN_VISITS{
+                continue;
+            }
+            visits[bb_index] += 1;
+            self.visit_times += 1;
+            let (nodes, constant_bool, constraint) = in_states[bb_index].clone().unwrap();
+            self.nodes = nodes;
+            self.constant_bool = constant_bool;
+            self.constraint = constraint;
+            let current_block = self.blocks[bb_index].clone();
+            let mut move_set = FxHashSet::default();
+            self.alias_check(bb_index, tcx, &mut move_set);
+            self.call_alias_check(bb_index, tcx, func_map, &mut move_set);
+            self.drop_check(bb_index, tcx, &mut move_set);
+
+            //finish the analysis for a path
+            if current_block.cfg_next.len() == 0{
+                self.suspend_state(bb_index);
+                if Self::should_check(self.def_id){
+                    self.bug_check(&current_block);
+                }
+                let results_nodes = self.nodes.clone();
+                self.merge_results(results_nodes, current_block.is_cleanup);
+            }
+
+            let successors = self.successors(&current_block, &current_block.cfg_next, tcx);
+            let constraint_record = self.constraint.clone();
+            for (next_index, constant) in successors{
+                let mut constant_bool = self.constant_bool.clone();
+                if let Some((discr, value)) = constant{
+                    constant_bool.insert(discr, value);
+                }
+                self.constraint = constraint_record.clone();
+                if self.assume_edge(&current_block, next_index, tcx) == false{
+                    continue;
+                }
+                let constraint = self.constraint.clone();
+                let new_state = match in_states[next_index].take(){
+                    None => (self.nodes.clone(), constant_bool, constraint),
+                    Some((old_nodes, old_constant, old_constraint)) => {
+                        let old_key = state_key(&old_nodes, &old_constant, &old_constraint);
+                        let joined_nodes = join_nodes(&old_nodes, &self.nodes, base);
+                        let joined_constant = join_constant(&old_constant, &constant_bool);
+                        let joined_constraint = old_constraint.join(&constraint);
+                        // the state at the block is stable.
+                        if state_key(&joined_nodes, &joined_constant, &joined_constraint) == old_key{
+                            in_states[next_index] = Some((joined_nodes, joined_constant, joined_constraint));
+                            continue;
+                        }
+                        (joined_nodes, joined_constant, joined_constraint)
+                    },
+                };
+                in_states[next_index] = Some(new_state);
+                worklist.push_back(next_index);
+            }
+        }
+    }
+}
+
+//the functions to be analyzed in the join-based mode, given by SAFEDROP_JOIN as a comma separated
+//list of function paths, or "*" for all the functions.
+pub fn join_selected<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> bool{
+    let selected = match env::var("SAFEDROP_JOIN"){
+        Ok(selected) => selected,
+        Err(_) => return false,
+    };
+    if selected.trim() == "*"{
+        return true;
+    }
+    let path = tcx.def_path_str(def_id);
+    return selected.split(',').any(|name| name.trim() == path);
+}
+
+//the stable identity of a node: the local or the static it is derived from, and the path of fields and
+//dereferences (None) from there. it does not depend on the order in which the nodes are created on a path.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum NodeRoot{
+    Local(usize),
+    Static(DefId),
+}
+
+fn node_key(nodes: &Vec<Node>, node: usize, base: usize) -> Option<(NodeRoot, Vec<Option<FieldKey>>)>{
+    let root = nodes[node].index;
+    let (root_key, mut path) = if root < base{
+        (NodeRoot::Local(root), Vec::new())
+    }
+    else if let Some(def_id) = nodes[root].global{
+        (NodeRoot::Static(def_id), Vec::new())
+    }
+    // the node created for the dereference of a pointer.
+    else if nodes[root].father < root{
+        let (root_key, mut path) = node_key(nodes, nodes[root].father, base)?;
+        path.push(None);
+        (root_key, path)
+    }
+    else{
+        return None;
+    };
+    path.extend(nodes[node].field_info.iter().map(|field| Some(*field)));
+    return Some((root_key, path));
+}
+
+//merge the nodes of two states at a join point. the first base nodes stand for the same locals in both states,
+//the nodes created later are matched by their stable identity, so that joining the same states again adds no node.
+//a node dead on one side only is alive but may be dead (may liveness), it is dead only if dead on both sides (must liveness).
+pub fn join_nodes(left: &Vec<Node>, right: &Vec<Node>, base: usize) -> Vec<Node>{
+    let mut result = left.clone();
+    let mut map = FxHashMap::default();
+    for i in 0..base{
+        map.insert(i, i);
+    }
+    let mut left_keys = FxHashMap::default();
+    for l in base..left.len(){
+        if let Some(key) = node_key(left, l, base){
+            left_keys.entry(key).or_insert(l);
+        }
+    }
+    // the nodes without a matching node in the left state are appended.
+    let mut appended = Vec::new();
+    for r in base..right.len(){
+        match node_key(right, r, base).and_then(|key| left_keys.get(&key)){
+            Some(l) => {
+                map.insert(r, *l);
+            },
+            None => {
+                map.insert(r, result.len() + appended.len());
+                appended.push(r);
+            },
+        }
+    }
+    for r in appended.iter(){
+        let mut node = right[*r].clone();
+        node.local = map[r];
+        node.index = *map.get(&node.index).unwrap_or(&node.index);
+        node.father = *map.get(&node.father).unwrap_or(&node.father);
+        node.alias = node.alias.iter().map(|i| map[i]).collect();
+        node.sons = node.sons.iter().map(|son| (*son.0, map[son.1])).collect();
+        result.push(node);
+    }
+    for r in 0..right.len(){
+        let l = map[&r];
+        if l >= left.len(){
+            continue;
+        }
+        let (l_alive, r_alive) = (result[l].alive, right[r].alive);
+        if l_alive == -1 && r_alive == -1{
+            result[l].maybe_dead = false;
+        }
+        else if l_alive == -1 || r_alive == -1{
+            result[l].alive = l_alive.max(r_alive);
+            result[l].maybe_dead = true;
+        }
+        else{
+            result[l].alive = l_alive.max(r_alive);
+            result[l].maybe_dead = result[l].maybe_dead || right[r].maybe_dead;
+        }
+        result[l].moved = result[l].moved && right[r].moved;
+        for alias in right[r].alias.iter(){
+            if result[l].alias.contains(&map[alias]) == false{
+                result[l].alias.push(map[alias]);
+            }
+        }
+        for (field, r_son) in right[r].sons.iter(){
+            if result[l].sons.contains_key(field) == false{
+                result[l].sons.insert(*field, map[r_son]);
+            }
+        }
+    }
+    return result;
+}
+
+//only the constants agreed by both states are kept.
+pub fn join_constant(left: &FxHashMap<usize, usize>, right: &FxHashMap<usize, usize>) -> FxHashMap<usize, usize>{
+    let mut result = FxHashMap::default();
+    for (discr, value) in left.iter(){
+        if right.get(discr) == Some(value){
+            result.insert(*discr, *value);
+        }
+    }
+    return result;
+}
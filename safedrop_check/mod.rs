@@ -1,18 +1,38 @@
 //This module should put under the directory: rust/compiler/rustc_mir_transform/safedrop_check
 
 use rustc_middle::ty::TyCtxt;
-use rustc_middle::ty;
 use rustc_middle::mir::Operand;
+use rustc_middle::mir::SourceInfo;
 use rustc_middle::mir::terminator::TerminatorKind;
+use rustc_span::def_id::DefId;
 use rustc_data_structures::fx::FxHashSet;
+use rustc_data_structures::fx::FxHashMap;
+use graph::BlockNode;
+use graph::Assignment;
+use graph::LoopFrame;
+use graph::LoopExit;
 pub mod graph;
 pub mod node;
 pub mod tools;
 pub mod corner_handle;
+pub mod indirect_call;
+pub mod summary_store;
+pub mod incremental;
+pub mod join;
+pub mod constraint;
+pub mod generator;
+pub mod global;
 pub use graph::SafeDropGraph;
 pub use node::*;
 pub use tools::*;
 pub use corner_handle::*;
+pub use indirect_call::*;
+pub use summary_store::*;
+pub use incremental::*;
+pub use join::*;
+pub use constraint::*;
+pub use generator::*;
+pub use global::*;
 pub use std::fmt;
 
 impl<'tcx> SafeDropGraph<'tcx>{
@@ -21,24 +41,68 @@ impl<'tcx> SafeDropGraph<'tcx>{
         for stmt in self.blocks[bb_index].const_value.clone(){
             self.constant_bool.insert(stmt.0, stmt.1);
         }
+        for definition in self.blocks[bb_index].definitions.iter(){
+            self.constraint.apply(definition);
+        }
         let current_block = self.blocks[bb_index].clone();
         for i in current_block.assignments{
-            let mut l_node_ref = self.handle_projection(false, i.left.local.as_usize(), tcx, i.left.clone());
-            let r_node_ref = self.handle_projection(true, i.right.local.as_usize(), tcx, i.right.clone());
-            if i.atype == 3{
-                self.nodes[l_node_ref].alias[0] = r_node_ref;
-                continue;
-            }
-            self.uaf_check(r_node_ref, i.span, i.right.local.as_usize(), false);
+            self.assign_check(&i, bb_index, tcx, move_set);
+        }        
+    }
+
+    // alias analysis for a single assignment
+    pub fn assign_check(&mut self, i: &Assignment<'tcx>, bb_index: usize, tcx: TyCtxt<'tcx>, move_set: &mut FxHashSet<usize>){
+        // the storage of the local ends.
+        if i.atype == 4{
+            self.storage_dead(i.left.local.as_usize());
+            return;
+        }
+        // a read through a pointer.
+        if i.atype == 5{
+            self.dangling_use_check(i.right.local.as_usize(), i.span);
+            return;
+        }
+        // the enum variant written to the place decides the later switchInt on its discriminant.
+        if i.atype == 7{
+            let l_node_ref = self.handle_projection(false, i.left.local.as_usize(), tcx, i.left.clone());
+            self.constant_bool.insert(l_node_ref, i.discr);
+            return;
+        }
+        // an aggregate rebuilds the place, the fields are assigned afterwards.
+        if i.atype == 6{
+            let l_node_ref = self.handle_projection(false, i.left.local.as_usize(), tcx, i.left.clone());
             self.fill_alive(l_node_ref, self.father_block[bb_index] as isize);
-            if i.atype == 2{
-                l_node_ref = *self.nodes[l_node_ref].sons.get(&0).unwrap() + 2;
-                self.nodes[l_node_ref].alive = self.father_block[bb_index] as isize;
-                self.nodes[l_node_ref-1].alive = self.father_block[bb_index] as isize;
-                self.nodes[l_node_ref-2].alive = self.father_block[bb_index] as isize;
+            self.constant_bool.remove(&l_node_ref);
+            self.nodes[l_node_ref].moved = false;
+            if self.nodes[l_node_ref].weak == false{
+                self.nodes[l_node_ref].alias = vec![l_node_ref];
+                self.nodes[l_node_ref].sons = FxHashMap::default();
             }
-            merge_alias(move_set, l_node_ref, r_node_ref, &mut self.nodes);
-        }        
+            return;
+        }
+        let mut l_node_ref = self.handle_projection(false, i.left.local.as_usize(), tcx, i.left.clone());
+        let r_node_ref = match i.right_global{
+            Some(static_id) => self.global_node(static_id, tcx),
+            None => self.handle_projection(true, i.right.local.as_usize(), tcx, i.right.clone()),
+        };
+        if i.atype == 3{
+            self.nodes[l_node_ref].alias[0] = r_node_ref;
+            return;
+        }
+        self.uaf_check(r_node_ref, i.span, i.right.local.as_usize(), false);
+        self.fill_alive(l_node_ref, self.father_block[bb_index] as isize);
+        // the variant held by the place before is overwritten.
+        self.constant_bool.remove(&l_node_ref);
+        if i.atype == 2{
+            l_node_ref = *self.nodes[l_node_ref].sons.get(&FieldKey::Field(0)).unwrap() + 2;
+            self.nodes[l_node_ref].alive = self.father_block[bb_index] as isize;
+            self.nodes[l_node_ref-1].alive = self.father_block[bb_index] as isize;
+            self.nodes[l_node_ref-2].alive = self.father_block[bb_index] as isize;
+        }
+        merge_alias(move_set, l_node_ref, r_node_ref, &mut self.nodes);
+        if i.atype == 1 && i.right.projection.is_empty(){
+            self.nodes[i.right.local.as_usize()].moved = true;
+        }
     }
 
     // interprocedure alias analysis, mainly handle the function call statement
@@ -46,101 +110,247 @@ impl<'tcx> SafeDropGraph<'tcx>{
         let current_block = self.blocks[bb_index].clone();
         for call in current_block.calls{
             if let TerminatorKind::Call { ref func, ref args, ref destination, target:_, cleanup: _, from_hir_call: _, fn_span: _ } = call.kind {
-                if let Operand::Constant(ref constant) = func {
-                    let left_ssa = self.handle_projection(false, destination.local.as_usize(), tcx, destination.clone());
-                    self.nodes[left_ssa].alive = self.father_block[bb_index] as isize;
-                    let mut merge_vec = Vec::new();
-                    merge_vec.push(left_ssa);
-                    let mut so_so_flag = 0;
-                    if self.nodes[left_ssa].so_so() {
-                        so_so_flag += 1;
-                    }
-                    for arg in args {
-                        match arg {
-                            Operand::Copy(ref p) => {
-                                let right_ssa = self.handle_projection(true, p.local.as_usize(), tcx, p.clone());
-                                self.uaf_check(right_ssa, call.source_info.span, p.local.as_usize(), true);
-                                merge_vec.push(right_ssa);
-                                if self.nodes[right_ssa].so_so() {
-                                    so_so_flag += 1;
-                                }
-                            },
-                            Operand::Move(ref p) => {
-                                let right_ssa = self.handle_projection(true, p.local.as_usize(), tcx, p.clone());
-                                self.uaf_check(right_ssa, call.source_info.span, p.local.as_usize(), true);
-                                merge_vec.push(right_ssa);
-                                if self.nodes[right_ssa].so_so() {
-                                    so_so_flag += 1;
-                                }
-                            },
-                            Operand::Constant(_) => {
+                let left_ssa = self.handle_projection(false, destination.local.as_usize(), tcx, destination.clone());
+                self.nodes[left_ssa].alive = self.father_block[bb_index] as isize;
+                self.nodes[left_ssa].moved = false;
+                let mut merge_vec = Vec::new();
+                merge_vec.push(left_ssa);
+                let mut so_so_flag = 0;
+                if self.nodes[left_ssa].so_so() {
+                    so_so_flag += 1;
+                }
+                for arg in args {
+                    match arg {
+                        Operand::Copy(ref p) => {
+                            let right_ssa = self.handle_projection(true, p.local.as_usize(), tcx, p.clone());
+                            self.uaf_check(right_ssa, call.source_info.span, p.local.as_usize(), true);
+                            merge_vec.push(right_ssa);
+                            if self.nodes[right_ssa].so_so() {
+                                so_so_flag += 1;
+                            }
+                        },
+                        Operand::Move(ref p) => {
+                            let right_ssa = self.handle_projection(true, p.local.as_usize(), tcx, p.clone());
+                            self.uaf_check(right_ssa, call.source_info.span, p.local.as_usize(), true);
+                            if p.projection.is_empty(){
+                                self.nodes[right_ssa].moved = true;
+                            }
+                            merge_vec.push(right_ssa);
+                            if self.nodes[right_ssa].so_so() {
+                                so_so_flag += 1;
+                            }
+                        },
+                        Operand::Constant(ref constant) => {
+                            // a pointer to a static.
+                            if let Some(static_id) = constant.check_static_ptr(tcx){
+                                let global = self.global_node(static_id, tcx);
+                                merge_vec.push(global);
+                                so_so_flag += 1;
+                            }
+                            else{
                                 merge_vec.push(0);
-                            },
+                            }
+                        },
+                    }
+                }
+                let targets = self.call_targets(func, &merge_vec, tcx);
+                if targets.is_empty(){
+                    continue;
+                }
+                self.constant_bool.remove(&left_ssa);
+                if targets.len() == 1{
+                    self.variant_handle(&merge_vec, targets[0], tcx);
+                }
+                // the models of the std APIs apply whatever the arguments are, e.g. a fresh allocation or a forgotten value.
+                if targets.len() == 1 && self.std_model(&merge_vec, move_set, targets[0], tcx, &call.source_info, self.father_block[bb_index] as isize){
+                    continue;
+                }
+                let drop_like = targets.iter().any(|target_id| Self::should_check(*target_id) == false);
+                if so_so_flag > 1 || (so_so_flag > 0 && drop_like){
+                    // indirect call with several candidates: apply the merged effect of their summaries.
+                    if targets.len() > 1{
+                        let mut summaries = Vec::new();
+                        let mut target_vecs = Vec::new();
+                        for target_id in targets.iter(){
+                            if tcx.is_mir_available(*target_id) == false{
+                                continue;
+                            }
+                            if let Some(summary) = self.callee_summary(*target_id, tcx, func_map){
+                                summaries.push(summary);
+                                target_vecs.push(self.target_args(*target_id, &merge_vec, func, args, tcx));
+                            }
                         }
+                        if summaries.is_empty() == false{
+                            let (merged, slots) = merge_summaries(&summaries, &target_vecs);
+                            self.apply_summary(&merged, &slots, move_set, &call.source_info, tcx);
+                        }
+                        continue;
                     }
-                    if let ty::FnDef(ref target_id, _) = constant.literal.ty().kind() {
-                        if so_so_flag > 1 || (so_so_flag > 0 && Self::should_check(target_id.clone()) == false){
-                            if tcx.is_mir_available(*target_id){
-                                if func_map.map.contains_key(&target_id.index.as_usize()){
-                                    let assignments = func_map.map.get(&target_id.index.as_usize()).unwrap();
-                                    for assign in assignments.assignments.iter(){
-                                        if !assign.valuable(){
-                                            continue;
-                                        }
-                                        merge(move_set, &mut self.nodes, assign, &merge_vec);
-                                    }
-                                    for dead in assignments.dead.iter(){
-                                        let drop = merge_vec[*dead];
-                                        self.dead_node(drop, 99999, &call.source_info, false);
-                                    }
-                                }
-                                else{
-                                    if func_map.set.contains(&target_id.index.as_usize()){
-                                        continue;
-                                    }
-                                    func_map.set.insert(target_id.index.as_usize());
-                                    let func_body = tcx.optimized_mir(*target_id);
-                                    let mut safedrop_graph = SafeDropGraph::new(&func_body, tcx, *target_id);
-                                    safedrop_graph.solve_scc();
-                                    safedrop_graph.safedrop_check(0, tcx, func_map);
-                                    let return_results = safedrop_graph.return_results.clone();
-                                    for assign in return_results.assignments.iter(){
-                                        if !assign.valuable(){
-                                            continue;
-                                        }
-                                        merge(move_set, &mut self.nodes, assign, &merge_vec);
-                                    }
-                                    for dead in return_results.dead.iter(){
-                                        let drop = merge_vec[*dead];
-                                        self.dead_node(drop, 99999, &call.source_info, false);
-                                    }
-                                    func_map.map.insert(target_id.index.as_usize(), return_results);
-                                }
+                    let target_id = targets[0];
+                    // the derived pointer is an interior pointer of the base pointer.
+                    if is_pointer_arith(tcx, target_id) && merge_vec.len() > 1{
+                        merge_alias(move_set, left_ssa, merge_vec[1], &mut self.nodes);
+                        continue;
+                    }
+                    if tcx.is_mir_available(target_id){
+                        if let Some(summary) = self.callee_summary(target_id, tcx, func_map){
+                            let target_vec = self.target_args(target_id, &merge_vec, func, args, tcx);
+                            self.apply_summary(&summary, &target_vec, move_set, &call.source_info, tcx);
+                        }
+                    }
+                    else{
+                        if self.nodes[left_ssa].so_so(){
+                            if self.corner_handle(left_ssa, &merge_vec, move_set, target_id){
+                                continue;
                             }
-                            else{
-                                if self.nodes[left_ssa].so_so(){
-                                    if self.corner_handle(left_ssa, &merge_vec, move_set, *target_id){
-                                        continue;
-                                    }
-                                    let mut right_set = Vec::new(); 
-                                    for right_ssa in &merge_vec{
-                                        if self.nodes[*right_ssa].so_so() && left_ssa != *right_ssa && self.nodes[left_ssa].is_ptr(){
-                                            right_set.push(*right_ssa);
-                                        }
-                                    }
-                                    if right_set.len() == 1{
-                                        merge_alias(move_set, left_ssa, right_set[0], &mut self.nodes);
-                                    }
+                            let mut right_set = Vec::new(); 
+                            for right_ssa in &merge_vec{
+                                if self.nodes[*right_ssa].so_so() && left_ssa != *right_ssa && self.nodes[left_ssa].is_ptr(){
+                                    right_set.push(*right_ssa);
                                 }
                             }
+                            if right_set.len() == 1{
+                                merge_alias(move_set, left_ssa, right_set[0], &mut self.nodes);
+                            }
                         }
                     }
                 }
             }
         }
     }
+
+    // fetch the return results of a callee, analyzing it on demand.
+    // recursive callees form a component of the call graph whose summaries are iterated to a fixpoint.
+    pub fn callee_summary(&mut self, target_id: DefId, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap) -> Option<ReturnResults>{
+        let key = target_id;
+        // recursive call: the callee is still under analysis, use its tentative summary.
+        if let Some(pos) = func_map.stack.iter().position(|f| *f == key){
+            func_map.mark_recursive(pos);
+            return func_map.map.get(&key).cloned();
+        }
+        if func_map.stale.contains(&key) == false{
+            if let Some(return_results) = func_map.map.get(&key){
+                // the tentative summary of an unfinished component: the caller depends on it and joins the component,
+                // it is recomputed with the component and not stored before the component converges.
+                if func_map.tentative.contains(&key){
+                    let root = func_map.low[&key];
+                    func_map.mark_recursive(root);
+                }
+                return Some(return_results.clone());
+            }
+        }
+        // the summaries are shared with other workers, wait for them rather than redoing their work.
+        match SUMMARY_STORE.claim(key, func_map.job) {
+            Claim::Done(return_results) => {
+                func_map.map.insert(key, return_results.clone());
+                return Some(return_results);
+            },
+            Claim::Owned => {
+                func_map.owned.insert(key);
+            },
+            Claim::Local => {},
+        }
+        func_map.stale.remove(&key);
+        let pos = func_map.stack.len();
+        func_map.stack.push(key);
+        func_map.low.insert(key, pos);
+        let tentative_begin = func_map.tentative.len();
+        let outer_changed = func_map.changed;
+        func_map.changed = false;
+        let mut round = 0;
+        loop{
+            round += 1;
+            // reuse the summary of the last build if the callee and its own callees are unchanged.
+            let cached = if round == 1 { cached_analysis(tcx, target_id, func_map, false) } else { None };
+            let return_results = match cached {
+                Some(entry) => entry.summary,
+                None => {
+                    let func_body = tcx.optimized_mir(target_id);
+                    let mut safedrop_graph = SafeDropGraph::new(&func_body, tcx, target_id);
+                    safedrop_graph.solve_scc();
+                    safedrop_graph.analyze(tcx, func_map);
+                    func_map.pending.insert(key, safedrop_graph.summary_deps(tcx));
+                    safedrop_graph.return_results.clone()
+                },
+            };
+            if let Some(old) = func_map.map.get(&key){
+                if !old.same(&return_results){
+                    func_map.changed = true;
+                }
+            }
+            else{
+                func_map.changed = true;
+            }
+            func_map.map.insert(key, return_results);
+            // part of an enclosing component, its root will iterate the summaries.
+            if func_map.low[&key] < pos{
+                func_map.tentative.push(key);
+                func_map.changed = outer_changed || func_map.changed;
+                break;
+            }
+            if func_map.recursive.contains(&key) == false || func_map.changed == false || round >= MAX_SCC_ROUNDS{
+                let mut members: Vec<DefId> = func_map.tentative.drain(tentative_begin..).collect();
+                members.push(key);
+                // only the converged summaries are stored for the next build.
+                if func_map.recursive.contains(&key) && func_map.changed{
+                    for member in members.iter(){
+                        func_map.pending.remove(member);
+                    }
+                }
+                record_component(tcx, &members, func_map);
+                for member in members{
+                    func_map.stale.remove(&member);
+                    func_map.low.remove(&member);
+                    if func_map.owned.remove(&member){
+                        SUMMARY_STORE.publish(member, func_map.map[&member].clone());
+                    }
+                }
+                func_map.changed = outer_changed;
+                break;
+            }
+            // recompute the whole component with the new tentative summaries.
+            for member in func_map.tentative.drain(tentative_begin..){
+                func_map.low.remove(&member);
+                func_map.stale.insert(member);
+            }
+            func_map.changed = false;
+        }
+        func_map.stack.pop();
+        // a tentative summary keeps the position of its component root until the component converges.
+        if func_map.low.get(&key) == Some(&pos){
+            func_map.low.remove(&key);
+        }
+        func_map.recursive.remove(&key);
+        return func_map.map.get(&key).cloned();
+    }
+
+    // instantiate the return results of a callee at the call site.
+    pub fn apply_summary(&mut self, return_results: &ReturnResults, merge_vec: &Vec<usize>, move_set: &mut FxHashSet<usize>, info: &SourceInfo, tcx: TyCtxt<'tcx>){
+        // the statics accessed by the callee are the same nodes in the caller.
+        let mut merge_vec = merge_vec.clone();
+        if merge_vec.len() == return_results.arg_size + 1{
+            for static_id in return_results.globals.iter(){
+                let global = self.global_node(*static_id, tcx);
+                merge_vec.push(global);
+            }
+        }
+        for assign in return_results.assignments.iter(){
+            if !assign.valuable(){
+                continue;
+            }
+            merge(move_set, &mut self.nodes, assign, merge_vec);
+        }
+        for dead in return_results.dead.iter(){
+            if *dead >= merge_vec.len(){
+                continue;
+            }
+            let drop = merge_vec[*dead];
+            self.dead_node(drop, 99999, info, false);
+        }
+    }
+
     // analyze the drop statement and update the alive state for nodes.
-    pub fn drop_check(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>){
+    pub fn drop_check(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>, move_set: &mut FxHashSet<usize>){
         let current_block = self.blocks[bb_index].clone();
         for drop in current_block.drops{
             match drop.kind{
@@ -150,50 +360,187 @@ impl<'tcx> SafeDropGraph<'tcx>{
                     let info = drop.source_info.clone();
                     self.dead_node(drop_local, life_begin, &info, false);
                 },
+                // drop the old value of the place, then assign the new value to it.
+                TerminatorKind::DropAndReplace{ref place, ref value, target: _, unwind: _} => {
+                    let life_begin = self.father_block[bb_index];
+                    let drop_local = self.handle_projection(false, place.local.as_usize(), tcx, place.clone());
+                    let info = drop.source_info.clone();
+                    self.dead_node(drop_local, life_begin, &info, false);
+                    match value {
+                        Operand::Copy(ref p) | Operand::Move(ref p) => {
+                            let atype = if let Operand::Move(_) = value { 1 } else { 0 };
+                            let right_local = self.handle_projection(true, p.local.as_usize(), tcx, p.clone());
+                            if self.nodes[drop_local].so_so() && self.nodes[right_local].so_so(){
+                                let assign = Assignment::new(place.clone(), p.clone(), atype, info.span);
+                                self.assign_check(&assign, bb_index, tcx, move_set);
+                            }
+                            else{
+                                self.fill_alive(drop_local, life_begin as isize);
+                            }
+                        },
+                        Operand::Constant(_) => {
+                            self.fill_alive(drop_local, life_begin as isize);
+                        },
+                    }
+                },
                 _ => {}
             }
         }
     }
 
-    // the core function of the safedrop.
-    pub fn safedrop_check(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap){
-        self.visit_times += 1;
+    // iterate the loop with the given head until the alias and alive states at its head are stable,
+    // so that the effects of an iteration reach the statements of the next iteration.
+    // the loop is entered at bb_index, frames holds the enclosing loops being explored.
+    // the paths leaving the loop are explored once after the iterations, each exit from every distinct state reaching it.
+    pub fn loop_fixpoint(&mut self, bb_index: usize, head: usize, mut frames: Vec<LoopFrame>, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap){
+        let depth = frames.len();
+        if depth == 0{
+            self.loop_visits = 0;
+        }
+        while self.loop_exits.len() <= depth{
+            self.loop_exits.push(FxHashMap::default());
+        }
+        let outer_exits = std::mem::take(&mut self.loop_exits[depth]);
+        frames.push(LoopFrame::new(head, self.nodes.len()));
+        self.loop_check(bb_index, frames, tcx, func_map);
+        let exits = std::mem::replace(&mut self.loop_exits[depth], outer_exits);
+        let mut exits: Vec<(usize, Vec<LoopExit>)> = exits.into_iter().collect();
+        exits.sort_by_key(|exit| exit.0);
+        let exits = exits.into_iter().flat_map(|(next_index, states)| states.into_iter().map(move |exit| (next_index, exit)));
+        let backup_nodes = self.nodes.clone();
+        let constant_record = self.constant_bool.clone();
+        let constraint_record = self.constraint.clone();
+        for (next_index, exit) in exits{
+            if self.visit_times > 10000{
+                break;
+            }
+            self.nodes = exit.nodes;
+            self.constant_bool = exit.constant_bool;
+            self.constraint = exit.constraint;
+            if exit.frames.is_empty(){
+                self.safedrop_check(next_index, tcx, func_map);
+            }
+            else{
+                self.loop_check(next_index, exit.frames, tcx, func_map);
+            }
+        }
+        self.nodes = backup_nodes;
+        self.constant_bool = constant_record;
+        self.constraint = constraint_record;
+    }
+
+    // explore the body of a loop path-sensitively in CFG order, starting from the loop head.
+    // an iteration ends when the path comes back to the loop head, and the state at the loop head
+    // is compared with the previous iterations. inner loops are analyzed as units inside their enclosing loop.
+    pub fn loop_check(&mut self, bb_index: usize, mut frames: Vec<LoopFrame>, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap){
+        // the loop is counted once as a visit of its SCC, the blocks inside it have their own threhold.
+        self.loop_visits += 1;
+        // the loop is not fully explored within the threhold, the function is over-visited and is analyzed in the join-based mode.
+        if self.loop_visits > MAX_LOOP_VISITS{
+            self.visit_times = self.visit_times.max(10001);
+        }
         if self.visit_times > 10000{
             return;
         }
-        let current_block = self.blocks[self.father_block[bb_index]].clone();
-        let mut move_set = FxHashSet::default();
-        self.alias_check(self.father_block[bb_index], tcx, &mut move_set);
-        self.call_alias_check(self.father_block[bb_index], tcx, func_map, &mut move_set);
-        self.drop_check(self.father_block[bb_index], tcx);
-        if current_block.sub_blocks.len() > 0{
-            for i in current_block.sub_blocks.clone(){
-                self.alias_check(i, tcx, &mut move_set);
-                self.call_alias_check(i, tcx,  func_map, &mut move_set);
-                self.drop_check(i, tcx);
+        // enter the outermost loop between the current frame and the block.
+        let top = frames.last().map(|frame| frame.head);
+        let mut entered = None;
+        let mut head = self.loop_head[bb_index];
+        while Some(head) != top{
+            entered = Some(head);
+            match self.head_parent.get(&head){
+                Some(parent) => head = *parent,
+                None => break,
             }
         }
+        if let Some(head) = entered{
+            self.loop_fixpoint(bb_index, head, frames, tcx, func_map);
+            return;
+        }
+        // the same state has been explored from this block in the same loop iterations.
+        let iterations = frames.iter().map(|frame| (frame.head, frame.rounds.clone())).collect();
+        if self.visited_states.insert((bb_index, self.state_key(self.nodes.len()), iterations)) == false{
+            return;
+        }
+        let frame = frames.last_mut().unwrap();
+        if bb_index == frame.head{
+            // widening: the nodes created inside the loop are not compared.
+            let state = self.state_key(frame.node_count);
+            if frame.rounds.contains(&state) || frame.rounds.len() >= MAX_LOOP_ROUNDS{
+                return;
+            }
+            frame.rounds.push(state);
+        }
+        let current_block = self.blocks[bb_index].clone();
+        let mut move_set = FxHashSet::default();
+        self.alias_check(bb_index, tcx, &mut move_set);
+        self.call_alias_check(bb_index, tcx, func_map, &mut move_set);
+        self.drop_check(bb_index, tcx, &mut move_set);
 
-        //finish the analysis for a path
-        if current_block.next.len() == 0{
-            // check the bugs.
-            if Self::should_check(self.def_id){
-                self.bug_check(&current_block);
+        let successors = self.successors(&current_block, &current_block.cfg_next, tcx);
+        for (next_index, constant) in successors.iter(){
+            if self.visit_times > 10000{
+                continue;
             }
-            // merge the result.
-            let results_nodes = self.nodes.clone();
-            self.merge_results(results_nodes, current_block.is_cleanup);
+            let backup_nodes = self.nodes.clone();
+            let constant_record = self.constant_bool.clone();
+            let constraint_record = self.constraint.clone();
+            if let Some((discr, value)) = constant{
+                self.constant_bool.insert(*discr, *value);
+            }
+            // the comparisons on the path contradict the branch.
+            if self.assume_edge(&current_block, *next_index, tcx) == false{
+                self.constant_bool = constant_record;
+                self.constraint = constraint_record;
+                continue;
+            }
+            // leave the loops which do not contain the next block.
+            let mut next_frames = frames.clone();
+            while let Some(frame) = next_frames.last(){
+                if self.in_loop(*next_index, frame.head){
+                    break;
+                }
+                next_frames.pop();
+            }
+            // leave the current loop, the exit is explored after the iterations.
+            if next_frames.len() < frames.len(){
+                let depth = next_frames.len();
+                let state = self.state_key(self.nodes.len());
+                let exits = self.loop_exits[depth].entry(*next_index).or_insert(Vec::new());
+                if exits.iter().all(|exit| exit.state != state){
+                    let exit = LoopExit{
+                        nodes: self.nodes.clone(),
+                        constant_bool: self.constant_bool.clone(),
+                        constraint: self.constraint.clone(),
+                        frames: next_frames,
+                        state: state,
+                    };
+                    exits.push(exit);
+                }
+            }
+            else{
+                self.loop_check(*next_index, next_frames, tcx, func_map);
+            }
+            self.nodes = backup_nodes;
+            self.constant_bool = constant_record;
+            self.constraint = constraint_record;
         }
+    }
 
-        
-        //search for the next block to visit.
+    // decide the blocks to visit after the current block, along with the constant value
+    // that the discriminant of a SwitchInt terminator takes on each edge.
+    pub fn successors(&mut self, current_block: &BlockNode<'tcx>, next: &FxHashSet<usize>, tcx: TyCtxt<'tcx>) -> Vec<(usize, Option<(usize, usize)>)>{
+        // only one path
+        if next.len() == 1{
+            return next.iter().map(|next_index| (*next_index, None)).collect();
+        }
         let mut loop_flag = true;
         let mut ans_bool = 0;
         let mut s_target = 0;
         let mut discr_target = 0;
         let mut s_targets = None;
         //handle the SwitchInt statement.
-        if current_block.switch_stmts.is_empty() == false && current_block.sub_blocks.is_empty(){
+        if current_block.switch_stmts.is_empty() == false{
             if let TerminatorKind::SwitchInt { ref discr, switch_ty: _, ref targets } = current_block.switch_stmts[0].clone().kind{
                 if let Some(p) = discr.place() {
                     let place = self.handle_projection(false, p.local.as_usize(), tcx, p.clone());
@@ -249,55 +596,91 @@ impl<'tcx> SafeDropGraph<'tcx>{
                 }
             }
         }
-        // only one path
-        if current_block.next.len() == 1{
-            for next_index in current_block.next{
-                self.safedrop_check(next_index, tcx, func_map);
+        // fixed path since a constant switchInt value
+        if loop_flag == false{
+            return vec![(s_target, None)];
+        }
+        let mut successors = Vec::new();
+        // Other cases in switchInt terminators
+        if let Some(targets) = s_targets{
+            for iter in targets.iter(){
+                successors.push((iter.1.as_usize(), Some((discr_target, iter.0 as usize))));
             }
+            let all_targets = targets.all_targets();
+            successors.push((all_targets[all_targets.len()-1].as_usize(), Some((discr_target, 99999 as usize))));
         }
         else{
-            // fixed path since a constant switchInt value
-            if loop_flag == false{
-                self.safedrop_check(s_target, tcx, func_map);
+            for next_index in next.iter(){
+                successors.push((*next_index, None));
             }
-            else{
-                // Other cases in switchInt terminators
-                if let Some(targets) = s_targets{
-                    for iter in targets.iter(){
-                        if self.visit_times > 10000{
-                            continue;
-                        }
-                        let next_index = iter.1.as_usize();
-                        let backup_nodes = self.nodes.clone();
-                        let constant_record = self.constant_bool.clone();
-                        self.constant_bool.insert(discr_target , iter.0 as usize);
-                        self.safedrop_check(next_index, tcx, func_map);
-                        self.nodes = backup_nodes;
-                        self.constant_bool = constant_record;
-                    }
-                    let all_targets = targets.all_targets();
-                    let next_index = all_targets[all_targets.len()-1].as_usize();
-                    let backup_nodes = self.nodes.clone();
-                    let constant_record = self.constant_bool.clone();
-                    self.constant_bool.insert(discr_target , 99999 as usize);
-                    self.safedrop_check(next_index, tcx, func_map);
-                    self.nodes = backup_nodes;
-                    self.constant_bool = constant_record;
-                }
-                else{
-                    for i in current_block.next{
-                        if self.visit_times > 10000{
-                            continue;
-                        }
-                        let next_index = i;
-                        let backup_nodes = self.nodes.clone();
-                        let constant_record = self.constant_bool.clone();
-                        self.safedrop_check(next_index, tcx, func_map);
-                        self.nodes = backup_nodes;
-                        self.constant_bool = constant_record;
-                    }
-                }
+            successors.sort();
+        }
+        return successors;
+    }
+
+    // the core function of the safedrop.
+    pub fn safedrop_check(&mut self, bb_index: usize, tcx: TyCtxt<'tcx>, func_map: &mut FuncMap){
+        self.visit_times += 1;
+        if self.visit_times > 10000{
+            return;
+        }
+        let root = self.father_block[bb_index];
+        // the same state has been explored from this block.
+        if self.visited_states.insert((root, self.state_key(self.nodes.len()), Vec::new())) == false{
+            return;
+        }
+        // a loop: explore its body from the loop head.
+        if self.blocks[root].sub_blocks.len() > 0{
+            self.loop_fixpoint(root, root, Vec::new(), tcx, func_map);
+            return;
+        }
+        let current_block = self.blocks[root].clone();
+        let mut move_set = FxHashSet::default();
+        self.alias_check(root, tcx, &mut move_set);
+        self.call_alias_check(root, tcx, func_map, &mut move_set);
+        self.drop_check(root, tcx, &mut move_set);
+
+        //finish the analysis for a path
+        if current_block.next.len() == 0{
+            self.suspend_state(root);
+            // check the bugs.
+            if Self::should_check(self.def_id){
+                self.bug_check(&current_block);
+            }
+            // merge the result.
+            if self.resumed == false{
+                let results_nodes = self.nodes.clone();
+                self.merge_results(results_nodes, current_block.is_cleanup);
+            }
+        }
+
+        //search for the next block to visit.
+        let successors = self.successors(&current_block, &current_block.next, tcx);
+        // only one path, or a fixed path since a constant switchInt value
+        if successors.len() == 1 && successors[0].1.is_none(){
+            self.safedrop_check(successors[0].0, tcx, func_map);
+            return;
+        }
+        for (next_index, constant) in successors.iter(){
+            if self.visit_times > 10000{
+                continue;
+            }
+            let backup_nodes = self.nodes.clone();
+            let constant_record = self.constant_bool.clone();
+            let constraint_record = self.constraint.clone();
+            if let Some((discr, value)) = constant{
+                self.constant_bool.insert(*discr, *value);
+            }
+            // the comparisons on the path contradict the branch.
+            if self.assume_edge(&current_block, *next_index, tcx) == false{
+                self.constant_bool = constant_record;
+                self.constraint = constraint_record;
+                continue;
             }
+            self.safedrop_check(*next_index, tcx, func_map);
+            self.nodes = backup_nodes;
+            self.constant_bool = constant_record;
+            self.constraint = constraint_record;
         }
     }
 }
\ No newline at end of file
@@ -1,4 +1,19 @@
 use rustc_data_structures::{fx::FxHashMap, stable_set::FxHashSet};
+use rustc_span::def_id::DefId;
+use rustc_span::Span;
+
+//the key of a son in the sons of a node.
+#[derive(Debug,Clone,Copy,PartialEq,Eq,PartialOrd,Ord,Hash)]
+pub enum FieldKey{
+    // a field of a struct or a tuple.
+    Field(usize),
+    // a field of an enum variant, keyed by the variant and the field index.
+    VariantField(usize, usize),
+    // an array element with a constant index.
+    Element(usize),
+    // the summarized node for the array elements with an unknown index.
+    AnyElement,
+}
 
 #[derive(Debug,Clone)]
 pub struct Node{
@@ -10,15 +25,25 @@ pub struct Node{
     pub father: usize,
     pub alias: Vec<usize>,
     pub alive: isize,
-    pub sons: FxHashMap<usize, usize>,
-    pub field_info: Vec<usize>,
+    // dead on some but not all of the paths merged at a join point.
+    pub maybe_dead: bool,
+    // a summarized node for the array elements with an unknown index, only weak updates are applied.
+    pub weak: bool,
+    // the value has been moved out of the local, its storage holds nothing.
+    pub moved: bool,
+    // the static or thread local that the node stands for.
+    pub global: Option<DefId>,
+    // where the node was dropped, reported with a later double free.
+    pub drop_span: Option<Span>,
+    pub sons: FxHashMap<FieldKey, usize>,
+    pub field_info: Vec<FieldKey>,
 }
 
 impl Node{
     pub fn new(index: usize, local: usize, need_drop: bool, so_so: bool) -> Node{
         let mut eq = Vec::new();
         eq.push(local);
-        Node { index: index, local: local, need_drop: need_drop, father: local, alias: eq, alive: 0, so_so: so_so, kind: 0, sons: FxHashMap::default(), field_info: Vec::<usize>::new()}
+        Node { index: index, local: local, need_drop: need_drop, father: local, alias: eq, alive: 0, maybe_dead: false, weak: false, moved: false, global: None, drop_span: None, so_so: so_so, kind: 0, sons: FxHashMap::default(), field_info: Vec::<FieldKey>::new()}
     }
 
     pub fn need_drop(&self) -> bool{
@@ -52,16 +77,20 @@ impl Node{
     pub fn is_corner_case(&self)-> bool{
         return self.kind == 3;
     }
+
+    pub fn is_manual(&self)-> bool{
+        return self.kind == 5;
+    }
 }
 
 #[derive(Debug,Clone)]
 pub struct ReturnAssign{
     pub left_index: usize,
-    pub left: Vec<usize>,
+    pub left: Vec<FieldKey>,
     pub left_so_so: bool, 
     pub left_need_drop: bool,
     pub right_index: usize,
-    pub right: Vec<usize>,
+    pub right: Vec<FieldKey>,
     pub right_so_so: bool, 
     pub right_need_drop: bool,
     pub atype: usize,
@@ -70,8 +99,8 @@ pub struct ReturnAssign{
 impl ReturnAssign{
     pub fn new(atype: usize, left_index: usize, left_so_so: bool, left_need_drop: bool,
         right_index: usize, right_so_so: bool, right_need_drop: bool) -> ReturnAssign{
-        let left = Vec::<usize>::new();
-        let right = Vec::<usize>::new();
+        let left = Vec::<FieldKey>::new();
+        let right = Vec::<FieldKey>::new();
         ReturnAssign{
             left_index: left_index,
             left: left,
@@ -95,12 +124,31 @@ pub struct ReturnResults{
     pub arg_size: usize,
     pub assignments: Vec<ReturnAssign>,
     pub dead: FxHashSet<usize>,
+    // the statics accessed by the function, the index arg_size + 1 + i stands for the i-th of them.
+    pub globals: Vec<DefId>,
 }
 
 impl ReturnResults {
     pub fn new(arg_size: usize) -> ReturnResults{
         let assignments = Vec::<ReturnAssign>::new();
         let dead = FxHashSet::default();
-        ReturnResults { arg_size: arg_size, assignments: assignments, dead: dead }
+        ReturnResults { arg_size: arg_size, assignments: assignments, dead: dead, globals: Vec::new() }
+    }
+
+    //compare two summaries regardless of the order of the assignments.
+    pub fn same(&self, other: &ReturnResults) -> bool{
+        if self.dead != other.dead || self.globals != other.globals || self.assignments.len() != other.assignments.len(){
+            return false;
+        }
+        let mut assign_set = FxHashSet::default();
+        for assign in self.assignments.iter(){
+            assign_set.insert((assign.left_index, assign.left.clone(), assign.right_index, assign.right.clone()));
+        }
+        for assign in other.assignments.iter(){
+            if !assign_set.contains(&(assign.left_index, assign.left.clone(), assign.right_index, assign.right.clone())){
+                return false;
+            }
+        }
+        return true;
     }
 }
@@ -0,0 +1,118 @@
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::thread::ThreadId;
+use rustc_span::def_id::DefId;
+use rustc_data_structures::fx::FxHashMap;
+use super::node::ReturnResults;
+
+
+//the result of claiming a callee summary in the shared store.
+pub enum Claim{
+    // the summary has been computed by some job.
+    Done(ReturnResults),
+    // the current job is responsible for computing the summary.
+    Owned,
+    // waiting for the summary would close a cycle among the jobs, or block the thread of the job computing it.
+    // the current job computes the summary itself without publishing it.
+    Local,
+}
+
+struct StoreState{
+    // the finished summaries.
+    done: FxHashMap<DefId, ReturnResults>,
+    // the job which is computing a summary, and the thread running it.
+    owner: FxHashMap<DefId, (usize, ThreadId)>,
+    // the summary that a job is waiting for.
+    waiting: FxHashMap<usize, DefId>,
+}
+
+impl StoreState{
+    fn new() -> StoreState{
+        StoreState { done: FxHashMap::default(), owner: FxHashMap::default(), waiting: FxHashMap::default() }
+    }
+
+    //follow the waits-for chain from the job, check whether it comes back to me.
+    fn leads_to(&self, job: usize, me: usize) -> bool{
+        let mut current = job;
+        for _ in 0..=self.waiting.len(){
+            if current == me{
+                return true;
+            }
+            match self.waiting.get(&current).and_then(|key| self.owner.get(key)){
+                Some(next) => current = next.0,
+                None => return false,
+            }
+        }
+        return false;
+    }
+}
+
+//summaries shared by the jobs of par_body_owners, each callee is published at most once.
+//a job is the check of one body owner, a worker thread may suspend a job and run another one.
+pub struct SummaryStore{
+    state: Mutex<Option<StoreState>>,
+    cond: Condvar,
+    jobs: AtomicUsize,
+}
+
+pub static SUMMARY_STORE: SummaryStore = SummaryStore::new();
+
+impl SummaryStore{
+    pub const fn new() -> SummaryStore{
+        SummaryStore { state: Mutex::new(None), cond: Condvar::new(), jobs: AtomicUsize::new(0) }
+    }
+
+    //a fresh id for the check of a body owner.
+    pub fn new_job(&self) -> usize{
+        return self.jobs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    //fetch the summary of a callee or become its owner, block while a job on another thread is computing it.
+    pub fn claim(&self, key: DefId, job: usize) -> Claim{
+        let me = thread::current().id();
+        let mut guard = self.state.lock().unwrap();
+        loop{
+            let state = guard.get_or_insert_with(StoreState::new);
+            if let Some(summary) = state.done.get(&key){
+                return Claim::Done(summary.clone());
+            }
+            let owner = match state.owner.get(&key){
+                Some(owner) => *owner,
+                None => {
+                    state.owner.insert(key, (job, me));
+                    return Claim::Owned;
+                },
+            };
+            if owner.0 == job{
+                return Claim::Owned;
+            }
+            // the owner is suspended below the current job on this thread, it can not finish while we wait.
+            if owner.1 == me || state.leads_to(owner.0, job){
+                return Claim::Local;
+            }
+            state.waiting.insert(job, key);
+            guard = self.cond.wait(guard).unwrap();
+            if let Some(state) = guard.as_mut(){
+                state.waiting.remove(&job);
+            }
+        }
+    }
+
+    //record the final summary of a callee and wake up the waiting jobs.
+    pub fn publish(&self, key: DefId, summary: ReturnResults){
+        let mut guard = self.state.lock().unwrap();
+        let state = guard.get_or_insert_with(StoreState::new);
+        state.owner.remove(&key);
+        state.done.insert(key, summary);
+        self.cond.notify_all();
+    }
+
+    //drop the summaries after the pass, the DefIds are only meaningful in the current session.
+    pub fn clear(&self){
+        let mut guard = self.state.lock().unwrap();
+        *guard = None;
+    }
+}
@@ -5,15 +5,22 @@ use rustc_middle::mir::Place;
 use rustc_middle::ty::TyCtxt;
 use rustc_middle::mir::ProjectionElem;
 use rustc_span::Span;
+use rustc_span::def_id::DefId;
 use rustc_data_structures::fx::FxHashMap;
 use rustc_data_structures::fx::FxHashSet;
 use super::Node;
+use super::node::FieldKey;
 use super::ReturnAssign;
 use super::ReturnResults;
 use super::SafeDropGraph;
 use super::corner_handle::is_corner_adt;
+use super::corner_handle::is_manual_adt;
 use super::graph::BlockNode;
+use super::constraint::Comparison;
+use super::constraint::PathConstraint;
+use super::summary_store::SUMMARY_STORE;
 pub use std::fmt;
+use std::fmt::Write;
 
 
 impl<'tcx> SafeDropGraph<'tcx>{
@@ -21,19 +28,26 @@ impl<'tcx> SafeDropGraph<'tcx>{
         if self.bug_records.is_bug_free(){
             return;
         }
-        println!("=================================");
-        println!("Function:{0:?};{1:?}", self.def_id, self.def_id.index);
-        self.bug_records.df_bugs_output();
-        self.bug_records.uaf_bugs_output();
-        self.bug_records.dp_bug_output(self.span);
-        
-        println!();
-        println!();
+        emit_warning(self.def_id, &self.warning_message());
+    }
+
+    // the bugs of the function in text, which can be cached and emitted again.
+    pub fn warning_message(&self) -> String{
+        let mut message = String::new();
+        if self.bug_records.is_bug_free(){
+            return message;
+        }
+        self.bug_records.df_bugs_output(&mut message);
+        self.bug_records.uaf_bugs_output(&mut message);
+        self.bug_records.dp_bug_output(self.span, &mut message);
+        return message;
     }
 
     // assign to the variable _x, we will set the alive of _x and its child nodes a new alive.
     pub fn fill_alive(&mut self, node: usize, alive: isize){
         self.nodes[node].alive = alive;
+        self.nodes[node].maybe_dead = false;
+        self.nodes[node].moved = false;
         //TODO: check the correctness.
         for i in self.nodes[node].alias.clone(){
             if self.nodes[i].alive == -1{
@@ -45,21 +59,22 @@ impl<'tcx> SafeDropGraph<'tcx>{
         }
     }
 
-    pub fn exist_dead(&self, node: usize, record: &mut FxHashSet<usize>, dangling: bool) -> bool{
+    //may: the nodes dead on some of the paths merged at a join point are also counted.
+    pub fn exist_dead(&self, node: usize, record: &mut FxHashSet<usize>, dangling: bool, may: bool) -> bool{
         //if is a dangling pointer check, only check the pointer type varible.
-        if self.nodes[node].is_alive() == false && (dangling && self.nodes[node].is_ptr() || !dangling){
+        if (self.nodes[node].is_alive() == false || may && self.nodes[node].maybe_dead) && (dangling && self.nodes[node].is_ptr() || !dangling){
             return true; 
         }
         record.insert(node);
         if self.nodes[node].alias[0] != node{
             for i in self.nodes[node].alias.clone().into_iter(){
-                if i != node && record.contains(&i) == false && self.exist_dead(i, record, dangling){
+                if i != node && record.contains(&i) == false && self.exist_dead(i, record, dangling, may){
                     return true;
                 }
             }
         }
         for i in self.nodes[node].sons.clone().into_iter(){
-            if record.contains(&i.1) == false && self.exist_dead(i.1, record, dangling){
+            if record.contains(&i.1) == false && self.exist_dead(i.1, record, dangling, may){
                 return true;
             }
         }
@@ -68,47 +83,129 @@ impl<'tcx> SafeDropGraph<'tcx>{
 
     pub fn df_check(&mut self, drop: usize, span: Span) -> bool{
         let root = self.nodes[drop].index;
-        if self.nodes[drop].is_alive() == false 
-        && self.bug_records.df_bugs.contains_key(&root) == false{
+        if self.nodes[drop].is_alive() == false && self.bug_records.df_bugs.contains_key(&root) == false{
             self.bug_records.df_bugs.insert(root, span.clone());
+            if let Some(first) = self.nodes[drop].drop_span{
+                self.bug_records.df_first.insert(root, first);
+            }
+        }
+        // dropped on some of the paths merged at a join point.
+        else if self.nodes[drop].maybe_dead && self.bug_records.df_possible.contains_key(&root) == false{
+            self.bug_records.df_possible.insert(root, span.clone());
         }
         return self.nodes[drop].is_alive() == false;
     }
 
     pub fn uaf_check(&mut self, used: usize, span: Span, origin: usize, is_func_call: bool){
-        let mut record = FxHashSet::default();
         if self.nodes[used].so_so() && (!self.nodes[used].is_ptr() || self.nodes[used].index != origin || is_func_call) 
-        && self.exist_dead(used, &mut record, false) == true 
-        && self.bug_records.uaf_bugs.contains(&span) == false{            
-            self.bug_records.uaf_bugs.insert(span.clone());
+        && self.bug_records.uaf_bugs.contains(&span) == false{
+            if self.exist_dead(used, &mut FxHashSet::default(), false, false){
+                self.bug_records.uaf_bugs.insert(span.clone());
+            }
+            else if self.exist_dead(used, &mut FxHashSet::default(), false, true){
+                self.bug_records.uaf_possible.insert(span.clone());
+            }
         }
     }
 
-    pub fn dp_check(&self, local: usize) -> bool{
+    //collect the field paths of the dead nodes reachable from the local, which escape the function.
+    pub fn dp_check(&mut self, local: usize, counted: bool, dangling: bool) -> bool{
         let mut record = FxHashSet::default();
-        return self.exist_dead(local, &mut record, local != 0);
+        let mut paths = Vec::new();
+        self.dead_paths(local, format!("_{}", local), counted, dangling, &mut record, &mut paths);
+        return self.record_dp_paths(paths);
     }
 
-    pub fn bug_check(&mut self, current_block: &BlockNode<'tcx>){
-        if current_block.is_cleanup == false{
-            if self.nodes[0].so_so() && self.dp_check(0){
-                self.bug_records.dp_bug = true;
+    fn record_dp_paths(&mut self, paths: Vec<String>) -> bool{
+        for path in paths.iter(){
+            if self.bug_records.dp_paths.contains(path) == false{
+                self.bug_records.dp_paths.push(path.clone());
+            }
+        }
+        return paths.is_empty() == false;
+    }
+
+    //the same search as exist_dead, but all the dead nodes are collected with their field paths.
+    //a node is only counted behind a reference unless counted is set, and only a pointer if dangling is set.
+    fn dead_paths(&self, node: usize, path: String, counted: bool, dangling: bool, record: &mut FxHashSet<usize>, paths: &mut Vec<String>){
+        if counted && (self.nodes[node].is_alive() == false || self.nodes[node].maybe_dead) && (dangling && self.nodes[node].is_ptr() || !dangling){
+            if self.nodes[node].is_alive(){
+                paths.push(format!("{} (possible)", path));
             }
             else{
-                for i in 0..self.arg_size{
-                    if self.nodes[i+1].is_ptr() && self.dp_check(i+1){
-                        self.bug_records.dp_bug = true;
-                    }
+                paths.push(path);
+            }
+            return;
+        }
+        record.insert(node);
+        if self.nodes[node].alias[0] != node{
+            let alias_path = if self.nodes[node].is_ptr() { format!("(*{})", path) } else { path.clone() };
+            let alias_counted = counted || self.nodes[node].is_ref();
+            for i in self.nodes[node].alias.clone().into_iter(){
+                if i != node && record.contains(&i) == false{
+                    self.dead_paths(i, alias_path.clone(), alias_counted, dangling, record, paths);
                 }
             }
         }
-        else{
-            for i in 0..self.arg_size{
-                if self.nodes[i+1].is_ptr() && self.dp_check(i+1){
-                    self.bug_records.dp_bug_unwind = true;
+        let mut sons: Vec<(FieldKey, usize)> = self.nodes[node].sons.iter().map(|son| (*son.0, *son.1)).collect();
+        sons.sort();
+        for (key, son) in sons{
+            if record.contains(&son) == false{
+                self.dead_paths(son, son_path(&path, key), counted, dangling, record, paths);
+            }
+        }
+    }
+
+    //the places reachable from the statics and the arguments outlive the function. a pointer argument is checked
+    //as a whole, and for the other arguments only the memory borrowed through the references in them.
+    pub fn escape_check(&mut self) -> bool{
+        let mut escaped = false;
+        // the statics outlive every function.
+        for i in 0..self.nodes.len(){
+            if let Some(static_id) = self.nodes[i].global{
+                let mut record = FxHashSet::default();
+                let mut paths = Vec::new();
+                self.dead_paths(i, format!("{:?}", static_id), true, true, &mut record, &mut paths);
+                if self.record_dp_paths(paths){
+                    escaped = true;
                 }
             }
         }
+        for i in 1..=self.arg_size{
+            let counted = self.nodes[i].is_ptr();
+            // the saved locals of a generator, the fields of its variants, are owned by the generator itself.
+            // its upvars outlive it.
+            if self.is_generator && i == 1{
+                let mut record: FxHashSet<usize> = self.nodes.iter()
+                    .flat_map(|node| node.sons.iter())
+                    .filter(|son| matches!(son.0, FieldKey::VariantField(..)))
+                    .map(|son| *son.1).collect();
+                let mut paths = Vec::new();
+                self.dead_paths(i, format!("_{}", i), counted, true, &mut record, &mut paths);
+                if self.record_dp_paths(paths){
+                    escaped = true;
+                }
+                continue;
+            }
+            if self.dp_check(i, counted, true){
+                escaped = true;
+            }
+        }
+        return escaped;
+    }
+
+    pub fn bug_check(&mut self, current_block: &BlockNode<'tcx>){
+        if current_block.is_cleanup == false{
+            if self.nodes[0].so_so() && self.dp_check(0, true, false){
+                self.bug_records.dp_bug = true;
+            }
+            else if self.escape_check(){
+                self.bug_records.dp_bug = true;
+            }
+        }
+        else if self.escape_check(){
+            self.bug_records.dp_bug_unwind = true;
+        }
     }
 
     pub fn dead_node(&mut self, drop: usize, life_begin: usize, info: &SourceInfo, alias: bool){
@@ -136,12 +233,57 @@ impl<'tcx> SafeDropGraph<'tcx>{
                 if self.nodes[drop].is_tuple() == true && self.nodes[i.1].need_drop() == false{
                     continue;
                 }
+                // the field in ManuallyDrop or MaybeUninit is not dropped with its owner.
+                if self.nodes[i.1].is_manual(){
+                    continue;
+                }
                 self.dead_node( i.1, life_begin, info, false);
             }
         }
         //SCC.
-        if self.nodes[drop].alive < life_begin as isize && self.nodes[drop].so_so(){
+        //a summarized element may stand for another element, it is not killed (weak update).
+        if self.nodes[drop].alive < life_begin as isize && self.nodes[drop].so_so() && self.nodes[drop].weak == false{
             self.nodes[drop].dead();   
+            self.nodes[drop].drop_span = Some(info.span);
+        }
+    }
+
+    //the storage of a stack local ends. the pointers to the local or to its fields become dangling,
+    //while the values copied out of it live on. a local whose value has been moved out holds nothing.
+    pub fn storage_dead(&mut self, local: usize){
+        if self.nodes[local].moved || self.nodes[local].is_ptr(){
+            return;
+        }
+        let mut storage = FxHashSet::default();
+        self.storage_nodes(local, &mut storage);
+        for i in 0..self.nodes.len(){
+            // the pointer arguments keep pointing to the objects of the caller.
+            let index = self.nodes[i].index;
+            if storage.contains(&i) || self.nodes[i].is_ptr() == false || (index > 0 && index <= self.arg_size){
+                continue;
+            }
+            if self.nodes[i].alias.iter().any(|alias| storage.contains(alias)){
+                self.nodes[i].dead();
+            }
+        }
+    }
+
+    //the node and its fields stored by value. a pointer field holds a value copied elsewhere and is not included,
+    //nor is a field whose value has been moved out, and the aliases of the node hold copies of its value in their own storage.
+    fn storage_nodes(&self, node: usize, storage: &mut FxHashSet<usize>){
+        storage.insert(node);
+        for son in self.nodes[node].sons.values(){
+            if self.nodes[*son].is_ptr() == false && self.nodes[*son].moved == false && storage.contains(son) == false{
+                self.storage_nodes(*son, storage);
+            }
+        }
+    }
+
+    //a read through a pointer whose pointee has gone out of scope.
+    pub fn dangling_use_check(&mut self, pointer: usize, span: Span){
+        if self.nodes[pointer].is_ptr() && self.nodes[pointer].is_alive() == false
+        && self.bug_records.uaf_bugs.contains(&span) == false{
+            self.bug_records.uaf_bugs.insert(span.clone());
         }
     }
 
@@ -150,75 +292,179 @@ impl<'tcx> SafeDropGraph<'tcx>{
     pub fn handle_projection(&mut self, is_right: bool, local: usize, tcx: TyCtxt<'tcx>, place: Place<'tcx>) -> usize{
         let mut init_local = local;
         let mut current_local = local;
+        // the type of the current place, used to create the element nodes.
+        let mut current_ty = self.local_tys.get(local).cloned();
+        // the enum variant selected by a downcast, the fields of different variants are different nodes.
+        let mut variant = None;
+        // the indices after a subslice are relative to the subslice, whose start is not tracked.
+        let mut subslice = false;
         for projection in place.projection{
             match projection{
+                ProjectionElem::Downcast(_, variant_idx) => {
+                    variant = Some(variant_idx.as_usize());
+                    continue;
+                }
                 ProjectionElem::Deref => {
-                    if current_local == self.nodes[current_local].alias[0] && self.nodes[current_local].is_ref() == false{
-                        let need_drop = true;
-                        let so_so = true;
-                        let mut node = Node::new(self.nodes.len(), self.nodes.len(), need_drop, need_drop || !so_so);
-                        node.kind = 1; //TODO
-                        node.alive = self.nodes[current_local].alive;
-                        self.nodes[current_local].alias[0] = self.nodes.len();
-                        self.nodes.push(node);
-                    }
-                    current_local = self.nodes[current_local].alias[0];
+                    current_local = self.deref_node(current_local);
                     init_local = self.nodes[current_local].index;
+                    current_ty = current_ty.and_then(|ty| ty.builtin_deref(true)).map(|tm| tm.ty);
                 }
                 ProjectionElem::Field(field, ty) =>{
-                    let index = field.as_usize();
+                    let index = field_key(variant, field.as_usize());
                     if is_right && self.nodes[current_local].alias[0] != current_local{
                         current_local = self.nodes[current_local].alias[0];
                         init_local = self.nodes[current_local].index;
                     }
-                    if self.nodes[current_local].sons.contains_key(&index) == false{
-                        let param_env = tcx.param_env(self.def_id);
-                        let need_drop = ty.needs_drop(tcx, param_env);
-                        let so_so = so_so(ty);
-                        let mut node = Node::new(init_local, self.nodes.len(), need_drop, need_drop || !so_so);
-                        node.kind = kind(ty);
-                        node.alive = self.nodes[current_local].alive;
-                        node.field_info = self.nodes[current_local].field_info.clone();
-                        node.field_info.push(index);
-                        self.nodes[current_local].sons.insert(index, node.local);
-                        self.nodes.push(node);
-                    }
-                    current_local = *self.nodes[current_local].sons.get(&index).unwrap();
+                    current_local = self.son_node(current_local, init_local, index, Some(ty), tcx);
+                    current_ty = Some(ty);
+                }
+                // the elements with a constant index are different nodes,
+                // and the elements with an unknown index share a summarized node.
+                ProjectionElem::Index(index_local) => {
+                    let index_local = index_local.as_usize();
+                    let offset = if subslice || self.const_locals.contains(&index_local) == false { None }
+                        else { self.constant_bool.get(&index_local).cloned() };
+                    current_ty = current_ty.and_then(|ty| ty.builtin_index());
+                    current_local = self.son_node(current_local, init_local, element_key(offset), current_ty, tcx);
+                    subslice = false;
+                }
+                ProjectionElem::ConstantIndex { offset, min_length: _, from_end } => {
+                    let offset = if from_end || subslice { None } else { Some(offset as usize) };
+                    current_ty = current_ty.and_then(|ty| ty.builtin_index());
+                    current_local = self.son_node(current_local, init_local, element_key(offset), current_ty, tcx);
+                    subslice = false;
+                }
+                // a subslice is a part of the same array.
+                ProjectionElem::Subslice { .. } => {
+                    subslice = true;
                 }
                 _ => {}
             }
+            variant = None;
         }
         return current_local;
     }
 
+    // fetch the node a pointer points to, create it if the pointer does not alias anything yet.
+    pub fn deref_node(&mut self, pointer: usize) -> usize{
+        if pointer == self.nodes[pointer].alias[0] && self.nodes[pointer].is_ref() == false{
+            let need_drop = true;
+            let so_so = true;
+            let mut node = Node::new(self.nodes.len(), self.nodes.len(), need_drop, need_drop || !so_so);
+            node.kind = 1; //TODO
+            node.alive = self.nodes[pointer].alive;
+            // the pointer which the node is created for, it identifies the node when states are joined.
+            node.father = pointer;
+            self.nodes[pointer].alias[0] = self.nodes.len();
+            self.nodes.push(node);
+        }
+        return self.nodes[pointer].alias[0];
+    }
+
+    // fetch a reference to the node which is not held by any local, e.g. the one passed by a shim.
+    pub fn ref_node(&mut self, target: usize) -> usize{
+        let exist = self.nodes.iter().position(|node| node.is_ref() && node.index == node.local
+            && node.father == target && node.alias[0] == target);
+        if let Some(node) = exist{
+            return node;
+        }
+        let mut node = Node::new(self.nodes.len(), self.nodes.len(), false, true);
+        node.kind = 4;
+        node.alive = self.nodes[target].alive;
+        // the referenced node, it identifies the node when states are joined.
+        node.father = target;
+        node.alias[0] = target;
+        self.nodes.push(node);
+        return self.nodes.len() - 1;
+    }
+
+    // fetch the son of a node with the given key, create it if it does not exist.
+    pub fn son_node(&mut self, current_local: usize, init_local: usize, index: FieldKey, ty: Option<Ty<'tcx>>, tcx: TyCtxt<'tcx>) -> usize{
+        if self.nodes[current_local].sons.contains_key(&index) == false{
+            let mut node = match ty{
+                Some(ty) => {
+                    let param_env = tcx.param_env(self.def_id);
+                    let need_drop = ty.needs_drop(tcx, param_env);
+                    let so_so = so_so(ty);
+                    let mut node = Node::new(init_local, self.nodes.len(), need_drop, need_drop || !so_so);
+                    node.kind = kind(ty);
+                    node
+                },
+                None => {
+                    let father = &self.nodes[current_local];
+                    let mut node = Node::new(init_local, self.nodes.len(), father.need_drop(), father.so_so());
+                    node.kind = father.kind;
+                    node
+                },
+            };
+            node.alive = self.nodes[current_local].alive;
+            node.weak = self.nodes[current_local].weak || index == element_key(None);
+            node.field_info = self.nodes[current_local].field_info.clone();
+            node.field_info.push(index);
+            self.nodes[current_local].sons.insert(index, node.local);
+            self.nodes.push(node);
+        }
+        return *self.nodes[current_local].sons.get(&index).unwrap();
+    }
+
+    //the alias and alive states of the first node_count nodes, the recorded constants and the path constraint.
+    pub fn state_key(&self, node_count: usize) -> StateKey{
+        return state_key(&self.nodes[..node_count], &self.constant_bool, &self.constraint);
+    }
+
     //merge the result of current path to the final result.
     pub fn merge_results(&mut self, results_nodes: Vec<Node>, is_cleanup: bool){
         for node in results_nodes.iter(){
-            if node.index <= self.arg_size{
+            if let Some(left_index) = self.summary_index(&results_nodes, node.index){
                 if node.alias[0] != node.local || node.alias.len() > 1{
                     for alias in node.alias.clone(){
-                        if results_nodes[alias].index <= self.arg_size
-                        && !self.return_set.contains(&(node.local, alias))
+                        if !self.return_set.contains(&(node.local, alias))
                         && alias != node.local
                         && node.index != results_nodes[alias].index{
+                            let right_index = match self.summary_index(&results_nodes, results_nodes[alias].index){
+                                Some(right_index) => right_index,
+                                None => continue,
+                            };
                             self.return_set.insert((node.local, alias));
                             let left_node = node;
                             let right_node = &results_nodes[alias];
                             let mut new_assign = ReturnAssign::new(0, 
-                                left_node.index, left_node.so_so(), left_node.need_drop(),
-                                right_node.index, right_node.so_so(), right_node.need_drop());
+                                left_index, left_node.so_so(), left_node.need_drop(),
+                                right_index, right_node.so_so(), right_node.need_drop());
                             new_assign.left = left_node.field_info.clone();
                             new_assign.right = right_node.field_info.clone();
                             self.return_results.assignments.push(new_assign);
                         }
                     }
                 }
-                if node.is_ptr() && is_cleanup == false && node.is_alive() == false && node.local <= self.arg_size{
-                    self.return_results.dead.insert(node.local);
+                if node.is_ptr() && is_cleanup == false && node.is_alive() == false{
+                    if node.local <= self.arg_size{
+                        self.return_results.dead.insert(node.local);
+                    }
+                    else if node.local == node.index && node.global.is_some(){
+                        self.return_results.dead.insert(left_index);
+                    }
                 }
             }
         }
     }
+
+    //the index in the summary of the root node: the return value, an argument, or a static after the arguments.
+    pub fn summary_index(&mut self, results_nodes: &Vec<Node>, index: usize) -> Option<usize>{
+        if index <= self.arg_size{
+            return Some(index);
+        }
+        let static_id = results_nodes[index].global?;
+        let globals = &mut self.return_results.globals;
+        let position = match globals.iter().position(|g| *g == static_id){
+            Some(position) => position,
+            None => {
+                globals.push(static_id);
+                globals.len() - 1
+            },
+        };
+        return Some(self.arg_size + 1 + position);
+    }
 }
 
 pub fn kind<'tcx>(current_ty: Ty<'tcx>) -> usize {
@@ -230,6 +476,9 @@ pub fn kind<'tcx>(current_ty: Ty<'tcx>) -> usize {
             if is_corner_adt(format!("{:?}", adt_def)){
                 return 3;
             }
+            else if is_manual_adt(format!("{:?}", adt_def)){
+                return 5;
+            }
             else{
                 return 0;
             }
@@ -238,6 +487,54 @@ pub fn kind<'tcx>(current_ty: Ty<'tcx>) -> usize {
     }
 }
 
+//the key of a field in the sons of a node: the fields of a struct or tuple are keyed by their index,
+//and the fields of an enum variant are keyed by the variant and the index.
+pub fn field_key(variant: Option<usize>, field: usize) -> FieldKey{
+    match variant{
+        Some(variant) => FieldKey::VariantField(variant, field),
+        None => FieldKey::Field(field),
+    }
+}
+
+//the place of the son with the given key, printed in the MIR syntax.
+pub fn son_path(path: &String, key: FieldKey) -> String{
+    match key{
+        FieldKey::Field(field) => format!("{}.{}", path, field),
+        FieldKey::VariantField(variant, field) => format!("({} as variant#{}).{}", path, variant, field),
+        FieldKey::Element(offset) => format!("{}[{}]", path, offset),
+        FieldKey::AnyElement => format!("{}[_]", path),
+    }
+}
+
+//the key of an array element in the sons of a node, None for an unknown index.
+pub fn element_key(offset: Option<usize>) -> FieldKey{
+    match offset{
+        Some(offset) => FieldKey::Element(offset),
+        None => FieldKey::AnyElement,
+    }
+}
+
+//the canonical form of a path state, two states are equivalent exactly when their forms are equal.
+#[derive(Debug,Clone,PartialEq,Eq,Hash)]
+pub struct StateKey{
+    nodes: Vec<(isize, bool, bool, Vec<usize>, Vec<(FieldKey, usize)>)>,
+    constants: Vec<(usize, usize)>,
+    comparisons: Vec<(usize, Comparison)>,
+    facts: Vec<Comparison>,
+}
+
+pub fn state_key(nodes: &[Node], constant_bool: &FxHashMap<usize, usize>, constraint: &PathConstraint) -> StateKey{
+    let nodes = nodes.iter().map(|node| {
+        let mut sons: Vec<(FieldKey, usize)> = node.sons.iter().map(|son| (*son.0, *son.1)).collect();
+        sons.sort();
+        (node.alive, node.maybe_dead, node.moved, node.alias.clone(), sons)
+    }).collect();
+    let mut constants: Vec<(usize, usize)> = constant_bool.iter().map(|c| (*c.0, *c.1)).collect();
+    constants.sort();
+    let (comparisons, facts) = constraint.canonical();
+    return StateKey { nodes: nodes, constants: constants, comparisons: comparisons, facts: facts };
+}
+
 //type filter.
 pub fn so_so<'tcx>(current_ty: Ty<'tcx>) -> bool {
     match current_ty.kind() {
@@ -272,7 +569,8 @@ pub fn merge_alias(move_set: &mut FxHashSet<usize>, left_ssa: usize, right_ssa:
     if nodes[left_ssa].index == nodes[right_ssa].index{
         return;
     }
-    if move_set.contains(&left_ssa){
+    // a summarized element keeps its old alias (weak update).
+    if move_set.contains(&left_ssa) || nodes[left_ssa].weak{
         let mut alias_clone = nodes[right_ssa].alias.clone();
         nodes[left_ssa].alias.append(&mut alias_clone);
     }
@@ -310,7 +608,7 @@ pub fn merge(move_set: &mut FxHashSet<usize>, nodes: &mut Vec<Node>, assign: &Re
     let mut left_ssa = left_init;
     let mut right_ssa = right_init;
     for index in assign.left.iter(){
-        if nodes[left_ssa].sons.contains_key(&index) == false{
+        if nodes[left_ssa].sons.contains_key(index) == false{
             let need_drop = assign.left_need_drop;
             let so_so = assign.left_so_so;
             let mut node = Node::new(left_init, nodes.len(), need_drop, so_so);
@@ -321,14 +619,14 @@ pub fn merge(move_set: &mut FxHashSet<usize>, nodes: &mut Vec<Node>, assign: &Re
             nodes[left_ssa].sons.insert(*index, node.local);
             nodes.push(node);
         }
-        left_ssa = *nodes[left_ssa].sons.get(&index).unwrap();
+        left_ssa = *nodes[left_ssa].sons.get(index).unwrap();
     }
     for index in assign.right.iter(){
         if nodes[right_ssa].alias[0] != right_ssa{
             right_ssa = nodes[right_ssa].alias[0];
             right_init = nodes[right_ssa].index;
         }
-        if nodes[right_ssa].sons.contains_key(&index) == false{
+        if nodes[right_ssa].sons.contains_key(index) == false{
             let need_drop = assign.right_need_drop;
             let so_so = assign.right_so_so;
             let mut node = Node::new(right_init, nodes.len(), need_drop, so_so);
@@ -339,68 +637,156 @@ pub fn merge(move_set: &mut FxHashSet<usize>, nodes: &mut Vec<Node>, assign: &Re
             nodes[right_ssa].sons.insert(*index, node.local);
             nodes.push(node);
         }
-        right_ssa = *nodes[right_ssa].sons.get(&index).unwrap();
+        right_ssa = *nodes[right_ssa].sons.get(index).unwrap();
     }
     merge_alias(move_set, left_ssa, right_ssa, nodes);
 }
 
+//the max times to visit a block in the join-based mode.
+pub const MAX_JOIN_VISITS: usize = 10;
+
+//the max rounds to iterate the body of a loop.
+pub const MAX_LOOP_ROUNDS: usize = 5;
+
+//the max times to visit the blocks of an outermost loop, including its inner loops.
+pub const MAX_LOOP_VISITS: usize = 10000;
+
+//the max rounds to iterate the summaries of a recursive component.
+pub const MAX_SCC_ROUNDS: usize = 10;
+
 //struct to cache the results for analyzed functions.
 #[derive(Clone)]
 pub struct FuncMap {
-    pub map: FxHashMap<usize, ReturnResults>,
-    pub set: FxHashSet<usize>,
+    pub map: FxHashMap<DefId, ReturnResults>,
+    // the functions under analysis, used to detect recursive calls in the call graph.
+    pub stack: Vec<DefId>,
+    // the lowest stack position reachable from a function through recursive calls (tarjan), for the functions
+    // on the stack and the tentative summaries.
+    pub low: FxHashMap<DefId, usize>,
+    // the functions which are the target of a recursive call.
+    pub recursive: FxHashSet<DefId>,
+    // the summaries in map which depend on an unfinished recursive component.
+    pub tentative: Vec<DefId>,
+    // the tentative summaries which need to be recomputed in the current round.
+    pub stale: FxHashSet<DefId>,
+    // whether a tentative summary has changed in the current round.
+    pub changed: bool,
+    // the id of the job in the summary store, and the summaries it is responsible for publishing.
+    pub job: usize,
+    pub owned: FxHashSet<DefId>,
+    // the callees of the summaries computed in the current build, stored in the cache once their component converges.
+    pub pending: FxHashMap<DefId, Vec<DefId>>,
 }
 
 impl FuncMap{
     pub fn new() -> FuncMap{
-        FuncMap { map: FxHashMap::default(), set: FxHashSet::default()}
+        FuncMap {
+            map: FxHashMap::default(),
+            stack: Vec::new(),
+            low: FxHashMap::default(),
+            recursive: FxHashSet::default(),
+            tentative: Vec::new(),
+            stale: FxHashSet::default(),
+            changed: false,
+            job: SUMMARY_STORE.new_job(),
+            owned: FxHashSet::default(),
+            pending: FxHashMap::default(),
+        }
+    }
+
+    //a recursive call to the function at stack position pos: all functions above it are in the same component.
+    pub fn mark_recursive(&mut self, pos: usize){
+        self.recursive.insert(self.stack[pos]);
+        for i in pos+1..self.stack.len(){
+            let low = self.low.entry(self.stack[i]).or_insert(i);
+            if *low > pos{
+                *low = pos;
+            }
+        }
     }
 }
 
 //structure to record the existed bugs.
 pub struct BugRecords{
     pub df_bugs: FxHashMap<usize, Span>,
+    // the site where the memory of a double free was first dropped.
+    pub df_first: FxHashMap<usize, Span>,
     pub df_bugs_unwind: FxHashMap<usize, Span>,
     pub uaf_bugs: FxHashSet<Span>,
+    // the bugs on some of the paths merged at a join point, which may not happen on any real path.
+    pub df_possible: FxHashMap<usize, Span>,
+    pub uaf_possible: FxHashSet<Span>,
     pub dp_bug: bool,
     pub dp_bug_unwind: bool,
+    // the places holding the dangling pointers when the function returns.
+    pub dp_paths: Vec<String>,
 }
 
 impl BugRecords{
     pub fn new() -> BugRecords{
-        BugRecords { df_bugs: FxHashMap::default(), df_bugs_unwind: FxHashMap::default(), uaf_bugs: FxHashSet::default(), dp_bug: false, dp_bug_unwind: false}
+        BugRecords { df_bugs: FxHashMap::default(), df_first: FxHashMap::default(), df_bugs_unwind: FxHashMap::default(), uaf_bugs: FxHashSet::default(), df_possible: FxHashMap::default(), uaf_possible: FxHashSet::default(), dp_bug: false, dp_bug_unwind: false, dp_paths: Vec::new()}
     }
 
     pub fn is_bug_free(&self) -> bool{
-        return self.df_bugs.is_empty() && self.uaf_bugs.is_empty() && self.dp_bug == false && self.dp_bug_unwind == false;
+        return self.df_bugs.is_empty() && self.uaf_bugs.is_empty() && self.df_possible.is_empty() && self.uaf_possible.is_empty()
+        && self.dp_bug == false && self.dp_bug_unwind == false;
     }
 
-    pub fn df_bugs_output(&self){
-        if self.df_bugs.is_empty(){
-            return;
+    pub fn df_bugs_output(&self, message: &mut String){
+        if self.df_bugs.is_empty() == false{
+            writeln!(message, "Double Free Bugs Exist:").unwrap();
+            for i in self.df_bugs.iter(){
+                match self.df_first.get(i.0){
+                    Some(first) => writeln!(message, "occurs in {:?}, first dropped in {:?}", i.1, first).unwrap(),
+                    None => writeln!(message, "occurs in {:?}", i.1).unwrap(),
+                }
+            }
         }
-        println!("Double Free Bugs Exist:");
-        for i in self.df_bugs.iter(){
-            println!("occurs in {:?}", i.1);
+        if self.df_possible.is_empty() == false{
+            writeln!(message, "Possible Double Free Bugs Exist:").unwrap();
+            for i in self.df_possible.iter(){
+                writeln!(message, "occurs in {:?}", i.1).unwrap();
+            }
         }
     }
 
-    pub fn uaf_bugs_output(&self){
-        if self.uaf_bugs.is_empty(){
-            return;
+    pub fn uaf_bugs_output(&self, message: &mut String){
+        if self.uaf_bugs.is_empty() == false{
+            writeln!(message, "Use After Free Bugs Exist:").unwrap();
+            for i in self.uaf_bugs.iter(){
+                writeln!(message, "occurs in {:?}", i).unwrap();
+            }
         }
-        println!("Use After Free Bugs Exist:");
-        for i in self.uaf_bugs.iter(){
-            println!("occurs in {:?}", i);
+        if self.uaf_possible.is_empty() == false{
+            writeln!(message, "Possible Use After Free Bugs Exist:").unwrap();
+            for i in self.uaf_possible.iter(){
+                writeln!(message, "occurs in {:?}", i).unwrap();
+            }
         }
     }
 
-    pub fn dp_bug_output(&self, span: Span){
+    pub fn dp_bug_output(&self, span: Span, message: &mut String){
         if self.dp_bug{
-            println!("Dangling Pointer Bug Exist {:?}", span);
+            writeln!(message, "Dangling Pointer Bug Exist {:?}", span).unwrap();
         }
         if self.dp_bug_unwind{
-            println!("Dangling Pointer Bug Exist in Unwinding {:?}", span);
+            writeln!(message, "Dangling Pointer Bug Exist in Unwinding {:?}", span).unwrap();
         }
+        for path in self.dp_paths.iter(){
+            writeln!(message, "escapes through {}", path).unwrap();
+        }
+    }
+}
+
+//print the bugs of a function.
+pub fn emit_warning(def_id: DefId, message: &String){
+    if message.is_empty(){
+        return;
     }
+    println!("=================================");
+    println!("Function:{0:?};{1:?}", def_id, def_id.index);
+    print!("{}", message);
+    
+    println!();
+    println!();
 }
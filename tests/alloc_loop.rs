@@ -0,0 +1,16 @@
+// Each round allocates a fresh block after freeing the one of the previous round, the new
+// pointer is not the freed one.
+// expect-not: churn: Use After Free
+
+use std::alloc::{alloc, dealloc, Layout};
+
+pub fn churn(rounds: usize) {
+    let layout = Layout::new::<u64>();
+    for _ in 0..rounds {
+        unsafe {
+            let p = alloc(layout) as *mut u64;
+            *p = 1;
+            dealloc(p as *mut u8, layout);
+        }
+    }
+}
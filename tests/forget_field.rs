@@ -0,0 +1,15 @@
+// Forgetting a field leaks its buffer, which outlives the struct it was moved out of.
+// Without the model of mem::forget the pointer dies with the storage of the struct.
+// expect-not: leak_field: Dangling Pointer Bug Exist
+
+pub struct Holder {
+    pub v: Vec<u8>,
+    pub tag: u8,
+}
+
+pub fn leak_field() -> *mut u8 {
+    let mut h = Holder { v: vec![1, 2, 3], tag: 0 };
+    let p = h.v.as_mut_ptr();
+    std::mem::forget(h.v);
+    p
+}
@@ -0,0 +1,16 @@
+// A borrow of a local saved across an await escapes to a static; dropping the
+// future while it is suspended frees the local and leaves the static dangling.
+// expect: escape_across_await::{closure#0}: Dangling Pointer Bug Exist
+
+static mut LAST: *const String = std::ptr::null();
+
+async fn pending() {}
+
+pub async fn escape_across_await() {
+    let s = String::from("saved");
+    unsafe {
+        LAST = &s;
+    }
+    pending().await;
+    drop(s);
+}
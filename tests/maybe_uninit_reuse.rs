@@ -0,0 +1,15 @@
+// A slot made each round by MaybeUninit::uninit owns nothing, it is not the value dropped in
+// the slot of the previous round. Without the model the new slot inherits the dropped value.
+// expect-not: reuse: Use After Free
+
+use std::mem::MaybeUninit;
+
+pub fn reuse(rounds: usize) {
+    for _ in 0..rounds {
+        let mut slot = MaybeUninit::<Box<u32>>::uninit();
+        unsafe {
+            slot.as_mut_ptr().write(Box::new(1));
+            slot.assume_init_drop();
+        }
+    }
+}
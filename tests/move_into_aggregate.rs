@@ -0,0 +1,18 @@
+// A vector moved into a struct keeps its buffer, the end of the storage of the moved-out local
+// does not make the pointer to the buffer dangling.
+// expect-not: wrap: Dangling Pointer Bug Exist
+// expect-not: wrap: Use After Free
+
+pub struct W {
+    pub v: Vec<u8>,
+}
+
+pub fn wrap() -> (W, *mut u8) {
+    let mut v = vec![1, 2, 3];
+    let p = v.as_mut_ptr();
+    let w = W { v };
+    unsafe {
+        *p = 0;
+    }
+    (w, p)
+}
@@ -0,0 +1,37 @@
+#!/bin/sh
+# Compile each case with the rustc built with SafeDrop (see README) and check the reports of its functions.
+# A case states what is reported for a function with the lines
+#   // expect: <function>: <text>        the report of the function contains the text
+#   // expect-not: <function>: <text>    the report of the function does not contain the text
+# Usage: tests/run.sh [rustc]
+RUSTC=${1:-rustc}
+DIR=$(cd "$(dirname "$0")" && pwd)
+OUT=$(mktemp -d)
+failed=0
+for case in "$DIR"/*.rs; do
+    name=$(basename "$case" .rs)
+    report="$OUT/$name.txt"
+    "$RUSTC" --edition 2021 --crate-type lib --out-dir "$OUT" "$case" > "$report" 2>&1
+    grep -E '^// expect(-not)?: ' "$case" | while IFS= read -r line; do
+        kind=${line%%:*}
+        rest=${line#*: }
+        func=${rest%%: *}
+        text=${rest#*: }
+        # the report of a function starts at its header and ends at the next header.
+        found=$(awk -v f="::$func)" -v t="$text" '
+            /^Function:/ { inside = index($0, f) > 0 }
+            inside && index($0, t) > 0 { hit = 1 }
+            END { print hit ? "yes" : "no" }' "$report")
+        if [ "$kind" = "// expect" ] && [ "$found" = "no" ] || [ "$kind" = "// expect-not" ] && [ "$found" = "yes" ]; then
+            echo "FAIL $name: $line"
+            echo fail >> "$OUT/failed"
+        fi
+    done
+done
+if [ -e "$OUT/failed" ]; then
+    failed=1
+else
+    echo "all cases passed"
+fi
+rm -rf "$OUT"
+exit $failed